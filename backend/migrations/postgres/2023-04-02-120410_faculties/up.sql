@@ -0,0 +1,4 @@
+CREATE TABLE faculties (
+  uuid TEXT PRIMARY KEY NOT NULL,
+  name TEXT NOT NUll UNIQUE
+);
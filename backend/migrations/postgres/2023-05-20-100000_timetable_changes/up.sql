@@ -0,0 +1,16 @@
+CREATE TABLE timetable_changes (
+  id SERIAL PRIMARY KEY,
+  student_group TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  before TEXT, -- JSON of the event before the change
+  after TEXT, -- JSON of the event after the change
+  changed_at TEXT NOT NULL,
+  FOREIGN KEY(student_group) REFERENCES groups (uuid)
+);
+CREATE INDEX timetable_changes_group ON timetable_changes (student_group, changed_at);
+-- Drop duplicates accumulated by repeated scrapes, the diff expects one row per event
+DELETE FROM timetables WHERE id NOT IN (
+  SELECT MIN(id) FROM timetables
+  GROUP BY name, day, start_time, end_time, student_group, week_start,
+    room, teacher, kind, subgroup, link
+);
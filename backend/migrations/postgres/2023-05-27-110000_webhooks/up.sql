@@ -0,0 +1,20 @@
+CREATE TABLE webhooks (
+  id SERIAL PRIMARY KEY,
+  url TEXT NOT NULL,
+  secret TEXT NOT NULL, -- key of the HMAC signature of delivered payloads
+  student_group TEXT, -- changes of every group are delivered when NULL
+  created_at TEXT NOT NULL,
+  FOREIGN KEY(student_group) REFERENCES groups (uuid)
+);
+CREATE TABLE webhook_deliveries (
+  id SERIAL PRIMARY KEY,
+  webhook INTEGER NOT NULL,
+  change INTEGER NOT NULL,
+  status TEXT NOT NULL, -- Pending, Delivered or Dead
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TEXT NOT NULL,
+  last_error TEXT,
+  FOREIGN KEY(webhook) REFERENCES webhooks (id) ON DELETE CASCADE,
+  FOREIGN KEY(change) REFERENCES timetable_changes (id)
+);
+CREATE INDEX webhook_deliveries_due ON webhook_deliveries (status, next_attempt_at);
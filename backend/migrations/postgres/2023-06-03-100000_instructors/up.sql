@@ -0,0 +1,10 @@
+CREATE TABLE instructors (
+  id SERIAL PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE -- as written in the timetable, e.g. "Иванов И.И."
+);
+ALTER TABLE timetables ADD COLUMN instructor INTEGER REFERENCES instructors (id) ON DELETE SET NULL;
+CREATE INDEX timetables_instructor ON timetables (instructor, week_start);
+
+INSERT INTO instructors (name)
+SELECT DISTINCT teacher FROM timetables WHERE teacher IS NOT NULL ORDER BY teacher;
+UPDATE timetables SET instructor = (SELECT id FROM instructors WHERE name = timetables.teacher);
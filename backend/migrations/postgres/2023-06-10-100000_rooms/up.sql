@@ -0,0 +1,9 @@
+CREATE TABLE rooms (
+  name TEXT PRIMARY KEY NOT NULL, -- as written in the timetable, e.g. "ФМ 311"
+  building TEXT -- leading part of the name before a space, if any
+);
+CREATE INDEX timetables_room ON timetables (room, week_start);
+
+INSERT INTO rooms (name, building)
+SELECT DISTINCT room, CASE WHEN position(' ' IN room) > 0 THEN split_part(room, ' ', 1) END
+FROM timetables WHERE room IS NOT NULL;
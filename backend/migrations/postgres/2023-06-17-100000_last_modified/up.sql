@@ -0,0 +1,12 @@
+CREATE TABLE last_modified (
+  resource TEXT PRIMARY KEY NOT NULL, -- "faculties", "groups:{faculty_uuid}" or "timetable:{group_uuid}"
+  modified_at TEXT NOT NULL
+);
+
+-- Data stored before the modifications were tracked is considered modified now
+INSERT INTO last_modified (resource, modified_at)
+SELECT 'faculties', to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS') WHERE EXISTS (SELECT 1 FROM faculties);
+INSERT INTO last_modified (resource, modified_at)
+SELECT DISTINCT 'groups:' || faculty, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS') FROM groups;
+INSERT INTO last_modified (resource, modified_at)
+SELECT DISTINCT 'timetable:' || student_group, to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS') FROM timetables;
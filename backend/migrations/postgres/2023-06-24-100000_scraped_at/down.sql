@@ -0,0 +1 @@
+ALTER TABLE last_modified DROP COLUMN scraped_at;
@@ -0,0 +1,15 @@
+DROP INDEX timetables_group_day;
+ALTER TABLE timetables
+  ALTER COLUMN day TYPE TEXT USING CASE day
+    WHEN 1 THEN '"Monday"'
+    WHEN 2 THEN '"Tuesday"'
+    WHEN 3 THEN '"Wednesday"'
+    WHEN 4 THEN '"Thursday"'
+    WHEN 5 THEN '"Friday"'
+    WHEN 6 THEN '"Saturday"'
+  END,
+  ALTER COLUMN start_time TYPE TEXT USING to_char(start_time, 'HH24:MI'),
+  ALTER COLUMN end_time TYPE TEXT USING to_char(end_time, 'HH24:MI'),
+  ALTER COLUMN week_start TYPE TEXT USING to_char(week_start, 'YYYY-MM-DD'),
+  ALTER COLUMN kind TYPE TEXT USING '"' || kind || '"';
+ALTER TABLE timetables ALTER COLUMN week_start SET DEFAULT '1970-01-01';
@@ -0,0 +1,17 @@
+-- Days become ISO weekday numbers and class kinds plain names instead of JSON strings,
+-- times and weeks get native types
+ALTER TABLE timetables ALTER COLUMN week_start DROP DEFAULT;
+ALTER TABLE timetables
+  ALTER COLUMN day TYPE INTEGER USING CASE day
+    WHEN '"Monday"' THEN 1
+    WHEN '"Tuesday"' THEN 2
+    WHEN '"Wednesday"' THEN 3
+    WHEN '"Thursday"' THEN 4
+    WHEN '"Friday"' THEN 5
+    WHEN '"Saturday"' THEN 6
+  END,
+  ALTER COLUMN start_time TYPE TIME USING start_time::TIME,
+  ALTER COLUMN end_time TYPE TIME USING end_time::TIME,
+  ALTER COLUMN week_start TYPE DATE USING week_start::DATE,
+  ALTER COLUMN kind TYPE TEXT USING btrim(kind, '"');
+CREATE INDEX timetables_group_day ON timetables (student_group, week_start, day, start_time);
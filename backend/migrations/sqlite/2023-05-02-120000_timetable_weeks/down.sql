@@ -0,0 +1 @@
+ALTER TABLE timetables DROP COLUMN week_start;
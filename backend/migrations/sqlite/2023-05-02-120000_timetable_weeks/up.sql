@@ -0,0 +1,3 @@
+-- Events scraped before this migration have no known week, they are scraped anew
+DELETE FROM timetables;
+ALTER TABLE timetables ADD COLUMN week_start TEXT NOT NULL DEFAULT '1970-01-01';
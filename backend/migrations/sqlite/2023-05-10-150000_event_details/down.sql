@@ -0,0 +1,5 @@
+ALTER TABLE timetables DROP COLUMN link;
+ALTER TABLE timetables DROP COLUMN subgroup;
+ALTER TABLE timetables DROP COLUMN kind;
+ALTER TABLE timetables DROP COLUMN teacher;
+ALTER TABLE timetables DROP COLUMN room;
@@ -0,0 +1,5 @@
+ALTER TABLE timetables ADD COLUMN room TEXT;
+ALTER TABLE timetables ADD COLUMN teacher TEXT;
+ALTER TABLE timetables ADD COLUMN kind TEXT;
+ALTER TABLE timetables ADD COLUMN subgroup TEXT;
+ALTER TABLE timetables ADD COLUMN link TEXT;
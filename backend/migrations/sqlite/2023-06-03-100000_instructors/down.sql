@@ -0,0 +1,3 @@
+DROP INDEX timetables_instructor;
+ALTER TABLE timetables DROP COLUMN instructor;
+DROP TABLE instructors;
@@ -0,0 +1,2 @@
+DROP INDEX timetables_room;
+DROP TABLE rooms;
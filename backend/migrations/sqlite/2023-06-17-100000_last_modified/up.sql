@@ -0,0 +1,12 @@
+CREATE TABLE last_modified (
+  resource TEXT PRIMARY KEY NOT NULL, -- "faculties", "groups:{faculty_uuid}" or "timetable:{group_uuid}"
+  modified_at TEXT NOT NULL
+);
+
+-- Data stored before the modifications were tracked is considered modified now
+INSERT INTO last_modified (resource, modified_at)
+SELECT 'faculties', strftime('%Y-%m-%dT%H:%M:%S', 'now') WHERE EXISTS (SELECT 1 FROM faculties);
+INSERT INTO last_modified (resource, modified_at)
+SELECT DISTINCT 'groups:' || faculty, strftime('%Y-%m-%dT%H:%M:%S', 'now') FROM groups;
+INSERT INTO last_modified (resource, modified_at)
+SELECT DISTINCT 'timetable:' || student_group, strftime('%Y-%m-%dT%H:%M:%S', 'now') FROM timetables;
@@ -0,0 +1,4 @@
+-- When the resource was scraped the last time, whether it changed or not
+ALTER TABLE last_modified ADD COLUMN scraped_at TEXT;
+
+UPDATE last_modified SET scraped_at = modified_at;
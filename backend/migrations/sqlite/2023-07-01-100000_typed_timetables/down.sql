@@ -0,0 +1,35 @@
+CREATE TABLE timetables_text (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name TEXT NOT NULL,
+  day TEXT NOT NULL,
+  start_time TEXT NOT NULL,
+  end_time TEXT NOT NULL,
+  student_group TEXT NOT NULL,
+  week_start TEXT NOT NULL DEFAULT '1970-01-01',
+  room TEXT,
+  teacher TEXT,
+  kind TEXT,
+  subgroup TEXT,
+  link TEXT,
+  instructor INTEGER REFERENCES instructors (id) ON DELETE SET NULL,
+  FOREIGN KEY(student_group) REFERENCES groups (uuid)
+);
+
+INSERT INTO timetables_text
+SELECT id, name,
+  CASE day
+    WHEN 1 THEN '"Monday"'
+    WHEN 2 THEN '"Tuesday"'
+    WHEN 3 THEN '"Wednesday"'
+    WHEN 4 THEN '"Thursday"'
+    WHEN 5 THEN '"Friday"'
+    WHEN 6 THEN '"Saturday"'
+  END,
+  strftime('%H:%M', start_time), strftime('%H:%M', end_time), student_group, week_start,
+  room, teacher, '"' || kind || '"', subgroup, link, instructor
+FROM timetables;
+
+DROP TABLE timetables;
+ALTER TABLE timetables_text RENAME TO timetables;
+CREATE INDEX timetables_instructor ON timetables (instructor, week_start);
+CREATE INDEX timetables_room ON timetables (room, week_start);
@@ -0,0 +1,39 @@
+-- SQLite cannot change types of columns, so the table is rebuilt:
+-- days become ISO weekday numbers and class kinds plain names instead of JSON strings,
+-- times gain seconds, the format Diesel reads and writes TIME columns in
+CREATE TABLE timetables_typed (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  name TEXT NOT NULL,
+  day INTEGER NOT NULL, -- 1 is Monday
+  start_time TIME NOT NULL,
+  end_time TIME NOT NULL,
+  student_group TEXT NOT NULL,
+  week_start DATE NOT NULL,
+  room TEXT,
+  teacher TEXT,
+  kind TEXT,
+  subgroup TEXT,
+  link TEXT,
+  instructor INTEGER REFERENCES instructors (id) ON DELETE SET NULL,
+  FOREIGN KEY(student_group) REFERENCES groups (uuid)
+);
+
+INSERT INTO timetables_typed
+SELECT id, name,
+  CASE day
+    WHEN '"Monday"' THEN 1
+    WHEN '"Tuesday"' THEN 2
+    WHEN '"Wednesday"' THEN 3
+    WHEN '"Thursday"' THEN 4
+    WHEN '"Friday"' THEN 5
+    WHEN '"Saturday"' THEN 6
+  END,
+  time(start_time), time(end_time), student_group, week_start,
+  room, teacher, trim(kind, '"'), subgroup, link, instructor
+FROM timetables;
+
+DROP TABLE timetables;
+ALTER TABLE timetables_typed RENAME TO timetables;
+CREATE INDEX timetables_instructor ON timetables (instructor, week_start);
+CREATE INDEX timetables_room ON timetables (room, week_start);
+CREATE INDEX timetables_group_day ON timetables (student_group, week_start, day, start_time);
@@ -0,0 +1,281 @@
+//! Administrative commands run from the command line instead of the HTTP server,
+//! so the data can be backfilled and inspected on their own
+
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::{BufReader, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use chrono::{Duration, NaiveDate};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    database::{
+        models::{
+            week_start_of, Bundle, DatabaseStats, ImportReport, PruneStats, Uuid,
+            BUNDLE_FORMAT_VERSION,
+        },
+        Database,
+    },
+    export::{self, CsvMetadata, Table},
+    lessons,
+    scheduling::{self, ScrapeStats},
+    scraping,
+};
+
+pub use crate::export::Format as ExportFormat;
+
+pub enum ScrapeTarget {
+    Faculties,
+    /// Groups of every stored faculty
+    Groups,
+    /// Timetable of a single stored group
+    Timetable(Uuid),
+    /// Faculties, groups of every faculty and timetables of every group
+    All,
+}
+
+/// Prints the stats, returns the number of failures
+fn report(what: &str, stats: ScrapeStats) -> usize {
+    println!("{what}: {stats}");
+    stats.failed
+}
+
+/// Scrapes the target and stores it, fails if any part of it could not be scraped.
+/// Pending migrations are applied first, as the scraped data is stored in the latest schema
+pub async fn scrape(target: ScrapeTarget) -> anyhow::Result<()> {
+    let db = Database::from_env()?;
+    for version in db.run_migrations()? {
+        println!("Applied migration {version}");
+    }
+    let client = scraping::client_from_env();
+    let failed = match target {
+        ScrapeTarget::Faculties => report(
+            "Faculties",
+            scheduling::scrape_all_faculties(db, client.as_ref()).await,
+        ),
+        ScrapeTarget::Groups => report(
+            "Student groups",
+            scheduling::scrape_all_groups(db, client.as_ref()).await,
+        ),
+        ScrapeTarget::Timetable(group) => {
+            if db.get_group(&group)?.is_none() {
+                bail!("Group {group} is not stored, scrape groups first");
+            }
+            let timetable = scraping::scrape_timetable(client.as_ref(), &group).await?;
+            let diff = db.update_timetable(&timetable)?;
+            println!(
+                "Timetable of group {group}: {} added, {} removed, {} moved",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.moved.len()
+            );
+            0
+        }
+        // Whatever could be scraped is stored even if some of it fails
+        ScrapeTarget::All => {
+            report(
+                "Faculties",
+                scheduling::scrape_all_faculties(db.clone(), client.as_ref()).await,
+            ) + report(
+                "Student groups",
+                scheduling::scrape_all_groups(db.clone(), client.as_ref()).await,
+            ) + report(
+                "Timetables",
+                scheduling::scrape_all_timetables(db, client.as_ref()).await,
+            )
+        }
+    };
+    if failed > 0 {
+        bail!("Could not scrape {failed} of the requested entities, see the log for details");
+    }
+    Ok(())
+}
+
+/// Applies pending migrations of the database
+pub fn migrate() -> anyhow::Result<()> {
+    let applied = Database::from_env()?.run_migrations()?;
+    if applied.is_empty() {
+        println!("Database is up to date");
+    }
+    for version in applied {
+        println!("Applied migration {version}");
+    }
+    Ok(())
+}
+
+fn create(path: &Path) -> anyhow::Result<File> {
+    File::create(path).with_context(|| format!("Could not create {}", path.display()))
+}
+
+fn open(path: &Path) -> anyhow::Result<File> {
+    File::open(path).with_context(|| format!("Could not open {}", path.display()))
+}
+
+/// Writes the stored faculties, groups and events as a JSON bundle to the file or to stdout,
+/// or as CSV files of every table to the directory
+pub fn export(format: ExportFormat, output: Option<&Path>) -> anyhow::Result<()> {
+    let bundle = Database::from_env()?.export()?;
+    match (format, output) {
+        (ExportFormat::Json, Some(path)) => {
+            let mut writer = create(path)?;
+            serde_json::to_writer_pretty(&mut writer, &bundle)?;
+            writeln!(writer)?;
+        }
+        (ExportFormat::Json, None) => {
+            let mut writer = std::io::stdout().lock();
+            serde_json::to_writer_pretty(&mut writer, &bundle)?;
+            writeln!(writer)?;
+        }
+        (ExportFormat::Csv, Some(dir)) => {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+            for table in Table::ALL {
+                export::write_csv(&bundle, table, create(&dir.join(table.file_name()))?)?;
+            }
+            let metadata = CsvMetadata {
+                format_version: bundle.format_version,
+                metadata: bundle.metadata.clone(),
+            };
+            let mut writer = create(&dir.join(export::METADATA_FILE))?;
+            serde_json::to_writer_pretty(&mut writer, &metadata)?;
+            writeln!(writer)?;
+        }
+        (ExportFormat::Csv, None) => {
+            bail!("CSV files are written to a directory, pass it with --output")
+        }
+    }
+
+    if let Some(path) = output {
+        eprintln!(
+            "Exported {} faculties, {} groups and {} events to {}",
+            bundle.faculties.len(),
+            bundle.groups.len(),
+            bundle.timetables.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Tables without a file in the directory are read as empty
+fn read_table<T: DeserializeOwned>(dir: &Path, table: Table) -> anyhow::Result<Vec<T>> {
+    let path = dir.join(table.file_name());
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    export::read_csv(open(&path)?).with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// Reads a bundle from a JSON file or from a directory of CSV files written by [`export`]
+pub fn read_bundle(path: &Path) -> anyhow::Result<Bundle> {
+    let bundle = if path.is_dir() {
+        let CsvMetadata {
+            format_version,
+            metadata,
+        } = serde_json::from_reader(BufReader::new(open(&path.join(export::METADATA_FILE))?))
+            .context("Invalid metadata of the CSV files")?;
+        Bundle {
+            format_version,
+            metadata,
+            faculties: read_table(path, Table::Faculties)?,
+            groups: read_table(path, Table::Groups)?,
+            timetables: read_table(path, Table::Timetables)?,
+        }
+    } else {
+        serde_json::from_reader(BufReader::new(open(path)?))
+            .with_context(|| format!("Invalid bundle {}", path.display()))?
+    };
+
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        bail!(
+            "Bundle format version {} is newer than the supported {BUNDLE_FORMAT_VERSION}, \
+            upgrade the backend",
+            bundle.format_version
+        );
+    }
+    Ok(bundle)
+}
+
+impl Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for invalid in &self.invalid {
+            writeln!(f, "Invalid: {invalid}")?;
+        }
+        for conflict in &self.conflicts {
+            writeln!(f, "Conflict: {conflict}")?;
+        }
+        write!(
+            f,
+            "Imported {} faculties, {} groups and {} events, {} conflicts with the stored data",
+            self.faculties,
+            self.groups,
+            self.events,
+            self.conflicts.len()
+        )
+    }
+}
+
+/// Imports the bundle at the path, see [`Database::import`]
+pub fn import(path: &Path) -> anyhow::Result<()> {
+    let bundle = read_bundle(path)?;
+    let report = Database::from_env()?.import(&bundle)?;
+    println!("{report}");
+    if !report.invalid.is_empty() {
+        bail!("Bundle refers to unknown faculties or groups, nothing is imported");
+    }
+    Ok(())
+}
+
+impl Display for DatabaseStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Faculties:          {}", self.faculties)?;
+        writeln!(f, "Student groups:     {}", self.groups)?;
+        writeln!(f, "Events:             {}", self.events)?;
+        if let Some((first, last)) = self.weeks {
+            writeln!(f, "Weeks:              {first} to {last}")?;
+        }
+        writeln!(f, "Instructors:        {}", self.instructors)?;
+        writeln!(f, "Rooms:              {}", self.rooms)?;
+        writeln!(f, "Timetable changes:  {}", self.changes)?;
+        writeln!(f, "Webhooks:           {}", self.webhooks)?;
+        writeln!(f, "Pending deliveries: {}", self.pending_deliveries)?;
+        writeln!(f, "Dead deliveries:    {}", self.dead_deliveries)?;
+        match self.oldest_scrape {
+            Some(scraped_at) => write!(f, "Oldest scrape:      {scraped_at} UTC"),
+            None => write!(f, "Oldest scrape:      never"),
+        }
+    }
+}
+
+/// Prints the amount of the stored data
+pub fn stats() -> anyhow::Result<()> {
+    println!("{}", Database::from_env()?.get_stats()?);
+    Ok(())
+}
+
+/// Monday of the earliest week kept when weeks before the last `keep_weeks` are pruned
+pub fn prune_before(today: NaiveDate, keep_weeks: u32) -> NaiveDate {
+    week_start_of(today) - Duration::weeks(keep_weeks.into())
+}
+
+/// Deletes timetables and changes older than `keep_weeks` weeks before the current one
+pub fn prune(keep_weeks: u32) -> anyhow::Result<()> {
+    let before = prune_before(lessons::now().date_naive(), keep_weeks);
+    let PruneStats {
+        events,
+        changes,
+        deliveries,
+    } = Database::from_env()?.prune(&before)?;
+    println!(
+        "Deleted {events} events, {changes} timetable changes and {deliveries} webhook deliveries \
+        from before {before}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,21 @@
+use super::*;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 6, day).unwrap()
+}
+
+#[test]
+fn current_week_is_kept_whole() {
+    // Wednesday and Sunday of the week starting on Monday, 12 June
+    assert_eq!(prune_before(date(14), 0), date(12));
+    assert_eq!(prune_before(date(18), 0), date(12));
+}
+
+#[test]
+fn previous_weeks_are_kept() {
+    assert_eq!(prune_before(date(14), 1), date(5));
+    assert_eq!(
+        prune_before(date(14), 2),
+        NaiveDate::from_ymd_opt(2023, 5, 29).unwrap()
+    );
+}
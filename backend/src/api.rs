@@ -0,0 +1,237 @@
+//! Shapes shared by every API response and the OpenAPI specification of the API
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use actix_web::{
+    body::BoxBody, get, http::StatusCode, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use serde::Serialize;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi, ToSchema,
+};
+
+use crate::{database::DBError, routes, scraping::ScrapeError};
+
+/// Every route of the current API version is mounted under this prefix
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Absolute path of an API route
+pub fn url(path: impl Display) -> String {
+    format!("{API_PREFIX}{path}")
+}
+
+/// Related resources by their relation name, e.g. `"timetable": "/api/v1/groups/.../timetable"`
+pub type Links = BTreeMap<String, String>;
+
+pub fn links<const N: usize>(links: [(&str, String); N]) -> Links {
+    links
+        .into_iter()
+        .map(|(relation, url)| (relation.to_string(), url))
+        .collect()
+}
+
+/// Successful response
+#[derive(Serialize, ToSchema)]
+pub struct Envelope<T> {
+    pub data: T,
+    /// Related resources, absent when there are none
+    #[serde(skip_serializing_if = "Links::is_empty")]
+    pub links: Links,
+    /// Present when the data is outdated and is being scraped again,
+    /// fresh data is returned by one of the next requests
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+impl<T: Serialize> Envelope<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            links: Links::new(),
+            stale: false,
+        }
+    }
+
+    pub fn with_links(mut self, links: Links) -> Self {
+        self.links = links;
+        self
+    }
+
+    pub fn with_stale(mut self, stale: bool) -> Self {
+        self.stale = stale;
+        self
+    }
+}
+
+impl<T: Serialize> Responder for Envelope<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::Ok().json(self)
+    }
+}
+
+/// Item of a list together with the resources related to it
+#[derive(Serialize, ToSchema)]
+pub struct Linked<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub links: Links,
+}
+
+impl<T> Linked<T> {
+    pub fn new(item: T, links: Links) -> Self {
+        Self { item, links }
+    }
+}
+
+/// Failure of any route
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    /// Administrative route was requested without a valid `Auth-Token` header
+    Unauthorized,
+    NotFound(String),
+    Database(DBError),
+    Scrape(ScrapeError),
+}
+
+impl ApiError {
+    /// Machine-readable name of the error
+    fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized => "unauthorized",
+            Self::NotFound(_) => "not_found",
+            Self::Database(DBError::Unavailable(_)) => "database_unavailable",
+            Self::Database(_) => "database_error",
+            Self::Scrape(ScrapeError::Timeout) => "upstream_timeout",
+            Self::Scrape(ScrapeError::NoData(_)) => "upstream_no_data",
+            Self::Scrape(_) => "upstream_error",
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadRequest(msg) | Self::NotFound(msg) => write!(f, "{msg}"),
+            Self::Unauthorized => write!(f, "Valid 'Auth-Token' header is required"),
+            Self::Database(e) => write!(f, "{e}"),
+            Self::Scrape(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<DBError> for ApiError {
+    fn from(e: DBError) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<ScrapeError> for ApiError {
+    fn from(e: ScrapeError) -> Self {
+        log::warn!("Scraping failed: {e}");
+        Self::Scrape(e)
+    }
+}
+
+/// Body of every unsuccessful response
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetails,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorDetails {
+    /// Machine-readable name of the error, e.g. `not_found` or `upstream_timeout`
+    pub code: String,
+    pub message: String,
+}
+
+impl ResponseError for ApiError {
+    /// Unreachable RUDN website is reported as a bad gateway, so clients can retry later
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::NotFound(_) | Self::Scrape(ScrapeError::NoData(_)) => StatusCode::NOT_FOUND,
+            Self::Database(DBError::Unavailable(_)) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Scrape(ScrapeError::Timeout) => StatusCode::GATEWAY_TIMEOUT,
+            Self::Scrape(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorBody {
+            error: ErrorDetails {
+                code: self.code().to_string(),
+                message: self.to_string(),
+            },
+        })
+    }
+}
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+/// Declares the `Auth-Token` header required by administrative routes
+struct AdminToken;
+
+impl Modify for AdminToken {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Auth-Token"))),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "RUDN timetable API",
+        description = "Timetables of the RUDN University scraped from its website"
+    ),
+    servers((url = "/api/v1")),
+    paths(
+        routes::get_index,
+        routes::get_status,
+        routes::get_faculties,
+        routes::get_groups,
+        routes::search_groups,
+        routes::get_timetable,
+        routes::get_timetable_ics,
+        routes::get_changes,
+        routes::get_calendar,
+        routes::get_day,
+        routes::get_upcoming,
+        routes::get_merged_timetable,
+        routes::get_free_slots,
+        routes::get_instructors,
+        routes::get_instructor_timetable,
+        routes::get_instructor_timetable_ics,
+        routes::get_free_rooms,
+        routes::get_room_timetable,
+        routes::add_webhook,
+        routes::delete_webhook,
+        routes::get_dead_letters,
+        routes::get_export,
+        get_openapi,
+    ),
+    components(schemas(ErrorBody)),
+    modifiers(&AdminToken)
+)]
+pub struct ApiDoc;
+
+/// This route returns the OpenAPI 3 specification of the API
+#[utoipa::path(responses((status = 200, description = "OpenAPI 3 specification")))]
+#[get("/openapi.json")]
+pub async fn get_openapi() -> impl Responder {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests;
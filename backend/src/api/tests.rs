@@ -0,0 +1,75 @@
+use super::*;
+
+/// Collects every `$ref` of the specification
+fn refs(value: &serde_json::Value, found: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    serde_json::Value::String(target) if key == "$ref" => {
+                        found.push(target.clone())
+                    }
+                    _ => refs(value, found),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+        _ => {}
+    }
+}
+
+#[test]
+fn specification_references_only_declared_schemas() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    let mut found = vec![];
+    refs(&spec, &mut found);
+    assert!(!found.is_empty());
+    for target in found {
+        let name = target.trim_start_matches("#/components/schemas/");
+        assert!(
+            spec["components"]["schemas"].get(name).is_some(),
+            "{target} is not declared"
+        );
+    }
+}
+
+#[test]
+fn specification_describes_every_route() {
+    let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    assert_eq!(spec["servers"][0]["url"], API_PREFIX);
+    for path in [
+        "/faculties",
+        "/faculties/{faculty_uuid}/groups",
+        "/groups/{group_uuid}/timetable",
+        "/groups/{group_uuid}/day/{date}",
+        "/groups/{group_uuid}/upcoming",
+        "/calendar",
+        "/instructors/{instructor_id}/timetable.ics",
+        "/rooms/free",
+        "/webhooks/{id}",
+        "/openapi.json",
+        "/export",
+        "/status",
+    ] {
+        assert!(spec["paths"].get(path).is_some(), "{path} is not described");
+    }
+    assert_eq!(
+        spec["paths"]["/webhooks"]["post"]["security"][0]["admin_token"],
+        serde_json::json!([])
+    );
+}
+
+#[actix_web::test]
+async fn errors_share_one_shape() {
+    let response = ApiError::Scrape(ScrapeError::Timeout).error_response();
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+
+    let body = actix_web::body::to_bytes(response.into_body())
+        .await
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["code"], "upstream_timeout");
+    assert_eq!(body["error"]["message"], ScrapeError::Timeout.to_string());
+}
@@ -0,0 +1,91 @@
+//! Search of time windows when every one of several groups is free
+
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveTime};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::database::models::*;
+
+/// Start time of the first class, the same as in the timetable widget
+pub const FIRST_CLASS_START: NaiveTime = match NaiveTime::from_hms_opt(9, 0, 0) {
+    Some(time) => time,
+    None => unreachable!(),
+};
+/// Class duration including the break, in minutes
+pub const CLASS_MINUTES: i64 = 90;
+/// Number of classes fitting into a working day, the last one ends at 21:00
+pub const CLASSES_PER_DAY: i64 = 8;
+
+const DAYS: [Day; 6] = [
+    Day::Monday,
+    Day::Tuesday,
+    Day::Wednesday,
+    Day::Thursday,
+    Day::Friday,
+    Day::Saturday,
+];
+
+/// Window of consecutive class slots without classes
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FreeSlot {
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
+}
+
+impl FreeSlot {
+    pub fn minutes(&self) -> i64 {
+        (self.end_time - self.start_time).num_minutes()
+    }
+}
+
+/// Beginning and end of the class slot with the given number, counting from zero
+fn class_slot(number: i64) -> (NaiveTime, NaiveTime) {
+    let start = FIRST_CLASS_START + Duration::minutes(number * CLASS_MINUTES);
+    (start, start + Duration::minutes(CLASS_MINUTES))
+}
+
+/// Inverts the timetables over the working day: a class slot is free
+/// when no event of any timetable overlaps it, adjacent free slots are joined into windows.
+/// Only windows lasting at least `min_minutes` are returned, days without them are omitted
+pub fn free_slots(
+    timetables: &[HashMap<Day, Vec<Event>>],
+    min_minutes: i64,
+) -> HashMap<Day, Vec<FreeSlot>> {
+    let mut free = HashMap::new();
+    for day in DAYS {
+        let events = timetables
+            .iter()
+            .filter_map(|timetable| timetable.get(&day))
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut windows: Vec<FreeSlot> = vec![];
+        for number in 0..CLASSES_PER_DAY {
+            let (start, end) = class_slot(number);
+            let is_busy = events
+                .iter()
+                .any(|event| event.start_time < end && event.end_time > start);
+            if is_busy {
+                continue;
+            }
+            match windows.last_mut() {
+                Some(window) if window.end_time == start => window.end_time = end,
+                _ => windows.push(FreeSlot {
+                    start_time: start,
+                    end_time: end,
+                }),
+            }
+        }
+
+        windows.retain(|window| window.minutes() >= min_minutes);
+        if !windows.is_empty() {
+            free.insert(day, windows);
+        }
+    }
+    free
+}
+
+#[cfg(test)]
+mod tests;
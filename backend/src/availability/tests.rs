@@ -0,0 +1,78 @@
+use super::*;
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn event(group: &str, day: Day, start: NaiveTime, end: NaiveTime) -> Event {
+    Event {
+        start_time: start,
+        end_time: end,
+        ..Event::builder()
+            .name("Физика")
+            .group(group)
+            .day(day)
+            .build()
+    }
+}
+
+fn timetable(events: Vec<Event>) -> HashMap<Day, Vec<Event>> {
+    let mut timetable: HashMap<Day, Vec<Event>> = HashMap::new();
+    for event in events {
+        timetable.entry(event.day).or_default().push(event);
+    }
+    timetable
+}
+
+fn window(start: NaiveTime, end: NaiveTime) -> FreeSlot {
+    FreeSlot {
+        start_time: start,
+        end_time: end,
+    }
+}
+
+#[test]
+fn day_without_classes_is_free_entirely() {
+    let free = free_slots(&[], CLASS_MINUTES);
+
+    assert_eq!(free.len(), 6);
+    assert_eq!(free[&Day::Saturday], [window(time(9, 0), time(21, 0))]);
+}
+
+#[test]
+fn classes_of_every_group_occupy_their_slots() {
+    let a = timetable(vec![event("a", Day::Monday, time(9, 0), time(10, 20))]);
+    let b = timetable(vec![
+        event("b", Day::Monday, time(12, 0), time(13, 20)),
+        event("b", Day::Monday, time(13, 30), time(14, 50)),
+    ]);
+
+    let free = free_slots(&[a, b], CLASS_MINUTES);
+    assert_eq!(
+        free[&Day::Monday],
+        [
+            window(time(10, 30), time(12, 0)),
+            window(time(15, 0), time(21, 0)),
+        ]
+    );
+}
+
+#[test]
+fn class_off_the_grid_occupies_every_slot_it_overlaps() {
+    let a = timetable(vec![event("a", Day::Tuesday, time(10, 0), time(11, 0))]);
+
+    let free = free_slots(&[a], CLASS_MINUTES);
+    assert_eq!(free[&Day::Tuesday], [window(time(12, 0), time(21, 0))]);
+}
+
+#[test]
+fn short_windows_are_dropped() {
+    let a = timetable(vec![
+        event("a", Day::Friday, time(10, 30), time(11, 50)),
+        event("a", Day::Friday, time(13, 30), time(20, 0)),
+    ]);
+
+    let free = free_slots(&[a], 2 * CLASS_MINUTES);
+    assert!(!free.contains_key(&Day::Friday));
+    assert!(free.contains_key(&Day::Monday));
+}
@@ -0,0 +1,109 @@
+//! Conditional GET support, so clients polling the timetable do not download it again
+//! when nothing has changed since their last request
+
+use std::time::SystemTime;
+
+use actix_web::{
+    body::BoxBody,
+    http::header::{
+        CacheControl, CacheDirective, ETag, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch,
+        LastModified,
+    },
+    HttpMessage, HttpRequest, HttpResponse, Responder,
+};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Lists of faculties and groups are updated monthly at most
+pub const LIST_MAX_AGE: u32 = 60 * 60;
+/// Timetables are rescraped weekly, but may be refreshed on demand
+pub const TIMETABLE_MAX_AGE: u32 = 5 * 60;
+
+/// Response which is answered with `304 Not Modified`
+/// when the client already has the same representation
+pub struct Cached<T> {
+    pub body: T,
+    /// When the underlying data was modified the last time, if known
+    pub last_modified: Option<NaiveDateTime>,
+    /// Seconds the response may be reused without revalidation
+    pub max_age: u32,
+}
+
+impl<T: Serialize> Cached<T> {
+    pub fn new(body: T, last_modified: Option<NaiveDateTime>, max_age: u32) -> Self {
+        Self {
+            body,
+            last_modified,
+            max_age,
+        }
+    }
+}
+
+/// Strong entity tag of the serialized body
+pub fn entity_tag(body: &[u8]) -> EntityTag {
+    EntityTag::new_strong(hex::encode(Sha256::digest(body)))
+}
+
+/// Whether the client's copy identified by the request headers is still fresh.
+/// `If-Modified-Since` is ignored when `If-None-Match` is present, as RFC 9110 requires
+fn is_fresh(req: &HttpRequest, etag: &EntityTag, last_modified: Option<SystemTime>) -> bool {
+    if let Some(if_none_match) = req.get_header::<IfNoneMatch>() {
+        return match if_none_match {
+            IfNoneMatch::Any => true,
+            IfNoneMatch::Items(tags) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        };
+    }
+    match (req.get_header::<IfModifiedSince>(), last_modified) {
+        (Some(IfModifiedSince(since)), Some(last_modified)) => {
+            last_modified <= SystemTime::from(since)
+        }
+        _ => false,
+    }
+}
+
+impl<T: Serialize> Responder for Cached<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse<Self::Body> {
+        // Serializing through a value sorts keys of maps, so equal data gets equal tags
+        let body = match serde_json::to_value(&self.body).and_then(|v| serde_json::to_vec(&v)) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("Error: '{e}' while serializing a response");
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let etag = entity_tag(&body);
+        // HTTP dates have no fractions of a second
+        let last_modified = self.last_modified.map(|last_modified| {
+            let seconds = last_modified.and_utc().timestamp();
+            SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds.max(0) as u64)
+        });
+
+        let is_fresh = is_fresh(req, &etag, last_modified);
+        let mut response = if is_fresh {
+            HttpResponse::NotModified()
+        } else {
+            HttpResponse::Ok()
+        };
+        response
+            .insert_header(ETag(etag))
+            .insert_header(CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(self.max_age),
+            ]));
+        if let Some(last_modified) = last_modified {
+            response.insert_header(LastModified(HttpDate::from(last_modified)));
+        }
+
+        if is_fresh {
+            response.finish()
+        } else {
+            response.content_type("application/json").body(body)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
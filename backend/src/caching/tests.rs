@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    http::{header, StatusCode},
+    test::TestRequest,
+};
+use chrono::NaiveDate;
+
+use super::*;
+
+fn modified_at() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, 6, 17)
+        .unwrap()
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+}
+
+fn respond(req: TestRequest) -> HttpResponse {
+    let body = HashMap::from([("faculty", "Engineering academy"), ("group", "НПИбд-01-22")]);
+    Cached::new(body, Some(modified_at()), LIST_MAX_AGE).respond_to(&req.to_http_request())
+}
+
+fn header_value(response: &HttpResponse, name: header::HeaderName) -> String {
+    response
+        .headers()
+        .get(name)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+fn etag() -> String {
+    header_value(&respond(TestRequest::default()), header::ETAG)
+}
+
+#[test]
+fn fresh_request_gets_validators() {
+    let response = respond(TestRequest::default());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(etag().starts_with('"'));
+    assert_eq!(
+        header_value(&response, header::LAST_MODIFIED),
+        "Sat, 17 Jun 2023 10:00:00 GMT"
+    );
+    assert_eq!(
+        header_value(&response, header::CACHE_CONTROL),
+        "public, max-age=3600"
+    );
+}
+
+#[test]
+fn equal_data_gets_equal_etag() {
+    // Maps are iterated in a random order, the tag must not depend on it
+    assert_eq!(etag(), etag());
+}
+
+#[test]
+fn matching_etag_is_not_modified() {
+    let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, etag()));
+    let response = respond(req);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(header_value(&response, header::ETAG), etag());
+}
+
+#[test]
+fn other_etag_is_modified() {
+    let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, "\"outdated\""));
+    assert_eq!(respond(req).status(), StatusCode::OK);
+}
+
+#[test]
+fn if_modified_since_compares_dates() {
+    let not_modified = TestRequest::default()
+        .insert_header((header::IF_MODIFIED_SINCE, "Sat, 17 Jun 2023 10:00:00 GMT"));
+    assert_eq!(respond(not_modified).status(), StatusCode::NOT_MODIFIED);
+
+    let modified = TestRequest::default()
+        .insert_header((header::IF_MODIFIED_SINCE, "Sat, 17 Jun 2023 09:59:59 GMT"));
+    assert_eq!(respond(modified).status(), StatusCode::OK);
+}
+
+#[test]
+fn if_none_match_takes_precedence() {
+    let req = TestRequest::default()
+        .insert_header((header::IF_NONE_MATCH, "\"outdated\""))
+        .insert_header((header::IF_MODIFIED_SINCE, "Sat, 17 Jun 2023 10:00:00 GMT"));
+    assert_eq!(respond(req).status(), StatusCode::OK);
+}
@@ -0,0 +1,153 @@
+//! Academic calendar of the university: semesters with the weeks counted from their start
+//! and holidays, classes take place only on study days
+
+use std::{collections::HashMap, env, fs, path::Path};
+
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::database::models::{week_start_of, Day, Event};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Semester {
+    pub name: String,
+    /// First day of the semester
+    pub start: NaiveDate,
+    /// Last day of the semester, including the exams
+    pub end: NaiveDate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Holiday {
+    pub name: String,
+    pub start: NaiveDate,
+    /// Last day of a holiday lasting several days
+    #[serde(default)]
+    pub end: Option<NaiveDate>,
+}
+
+impl Holiday {
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.start <= date && date <= self.end.unwrap_or(self.start)
+    }
+}
+
+/// Semesters and holidays read from the calendar file.
+/// Every day is a study day when no semesters are configured,
+/// otherwise days between the semesters are breaks
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AcademicCalendar {
+    #[serde(default)]
+    pub semesters: Vec<Semester>,
+    #[serde(default)]
+    pub holidays: Vec<Holiday>,
+}
+
+/// Academic calendar of a single date
+#[derive(Debug, PartialEq, Eq, Serialize, ToSchema)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    /// Semester the date belongs to, absent during the breaks
+    pub semester: Option<Semester>,
+    /// Number of the week within the semester, the week the semester starts in is the first
+    pub academic_week: Option<u32>,
+    /// Holiday on the date
+    pub holiday: Option<Holiday>,
+    /// Whether classes take place on the date
+    pub study_day: bool,
+}
+
+impl AcademicCalendar {
+    /// Parses the calendar, semesters must not overlap and every period must end after it starts
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let mut calendar: Self = serde_json::from_str(json)?;
+        calendar.semesters.sort_by_key(|semester| semester.start);
+        for semester in &calendar.semesters {
+            if semester.end < semester.start {
+                bail!("Semester {} ends before it starts", semester.name);
+            }
+        }
+        for pair in calendar.semesters.windows(2) {
+            if pair[1].start <= pair[0].end {
+                bail!("Semesters {} and {} overlap", pair[0].name, pair[1].name);
+            }
+        }
+        for holiday in &calendar.holidays {
+            if holiday.end.is_some_and(|end| end < holiday.start) {
+                bail!("Holiday {} ends before it starts", holiday.name);
+            }
+        }
+        Ok(calendar)
+    }
+
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        Self::parse(&json).with_context(|| format!("Invalid academic calendar {}", path.display()))
+    }
+
+    /// Reads the calendar from the file at `CALENDAR_FILE`, the calendar is empty when it is unset
+    pub fn from_env() -> anyhow::Result<Self> {
+        match env::var("CALENDAR_FILE") {
+            Ok(path) => Self::from_file(Path::new(&path)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn semester_of(&self, date: NaiveDate) -> Option<&Semester> {
+        self.semesters
+            .iter()
+            .find(|semester| semester.start <= date && date <= semester.end)
+    }
+
+    pub fn holiday_on(&self, date: NaiveDate) -> Option<&Holiday> {
+        self.holidays.iter().find(|holiday| holiday.contains(date))
+    }
+
+    /// Number of the week containing the date within its semester, starting from 1
+    pub fn academic_week(&self, date: NaiveDate) -> Option<u32> {
+        let semester = self.semester_of(date)?;
+        let weeks = (week_start_of(date) - week_start_of(semester.start)).num_weeks();
+        u32::try_from(weeks + 1).ok()
+    }
+
+    /// Whether classes take place on the date, i.e. it is neither a Sunday,
+    /// nor a holiday, nor a day of a break between the semesters
+    pub fn is_study_day(&self, date: NaiveDate) -> bool {
+        Day::of(date).is_some()
+            && self.holiday_on(date).is_none()
+            && (self.semesters.is_empty() || self.semester_of(date).is_some())
+    }
+
+    pub fn day(&self, date: NaiveDate) -> CalendarDay {
+        CalendarDay {
+            date,
+            semester: self.semester_of(date).cloned(),
+            academic_week: self.academic_week(date),
+            holiday: self.holiday_on(date).cloned(),
+            study_day: self.is_study_day(date),
+        }
+    }
+
+    /// Whether the event is not cancelled by a holiday or a break
+    pub fn takes_place(&self, event: &Event) -> bool {
+        self.is_study_day(event.date())
+    }
+
+    /// Drops events on the days off from the timetable of a week, along with the emptied days
+    pub fn study_days_only(&self, timetable: HashMap<Day, Vec<Event>>) -> HashMap<Day, Vec<Event>> {
+        timetable
+            .into_iter()
+            .map(|(day, mut events)| {
+                events.retain(|event| self.takes_place(event));
+                (day, events)
+            })
+            .filter(|(_, events)| !events.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests;
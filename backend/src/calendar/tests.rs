@@ -0,0 +1,94 @@
+use super::*;
+
+fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn calendar() -> AcademicCalendar {
+    AcademicCalendar::parse(
+        r#"{
+            "semesters": [
+                {"name": "Весенний семестр 2027", "start": "2027-02-08", "end": "2027-06-30"},
+                {"name": "Осенний семестр 2026", "start": "2026-09-01", "end": "2027-01-31"}
+            ],
+            "holidays": [
+                {"name": "День народного единства", "start": "2026-11-04"},
+                {"name": "Новогодние каникулы", "start": "2026-12-31", "end": "2027-01-08"}
+            ]
+        }"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn counts_academic_weeks_across_new_year() {
+    let calendar = calendar();
+
+    // The autumn semester starts on a Tuesday, its week begins on the Monday before
+    assert_eq!(calendar.academic_week(date(2026, 9, 1)), Some(1));
+    assert_eq!(calendar.academic_week(date(2026, 9, 6)), Some(1));
+    assert_eq!(calendar.academic_week(date(2026, 9, 7)), Some(2));
+    assert_eq!(calendar.academic_week(date(2027, 1, 11)), Some(20));
+    assert_eq!(calendar.academic_week(date(2027, 2, 8)), Some(1));
+    assert_eq!(
+        calendar.semester_of(date(2027, 1, 11)).unwrap().name,
+        "Осенний семестр 2026"
+    );
+}
+
+#[test]
+fn days_off_are_holidays_breaks_and_sundays() {
+    let calendar = calendar();
+
+    assert!(calendar.is_study_day(date(2026, 11, 3)));
+    assert!(!calendar.is_study_day(date(2026, 11, 4)));
+    assert!(!calendar.is_study_day(date(2027, 1, 5)));
+    assert!(calendar.is_study_day(date(2027, 1, 9)));
+    assert!(!calendar.is_study_day(date(2026, 11, 8)));
+    // Break between the semesters and the summer holidays
+    assert!(!calendar.is_study_day(date(2027, 2, 3)));
+    assert!(!calendar.is_study_day(date(2027, 7, 5)));
+    assert_eq!(calendar.academic_week(date(2027, 2, 3)), None);
+
+    let day = calendar.day(date(2027, 1, 5));
+    assert_eq!(day.holiday.unwrap().name, "Новогодние каникулы");
+    assert_eq!(day.academic_week, Some(19));
+    assert!(!day.study_day);
+}
+
+#[test]
+fn every_day_but_sunday_is_a_study_day_without_semesters() {
+    let calendar = AcademicCalendar::parse("{}").unwrap();
+
+    assert!(calendar.is_study_day(date(2027, 7, 5)));
+    assert!(!calendar.is_study_day(date(2027, 7, 4)));
+    assert_eq!(calendar.academic_week(date(2027, 7, 5)), None);
+}
+
+#[test]
+fn timetables_skip_events_on_days_off() {
+    let week = date(2026, 11, 2);
+    let tuesday = Event::builder().week(week).day(Day::Tuesday).build();
+    let timetable = HashMap::from([
+        (Day::Tuesday, vec![tuesday.clone()]),
+        (
+            Day::Wednesday,
+            vec![Event::builder().week(week).day(Day::Wednesday).build()],
+        ),
+    ]);
+
+    let timetable = calendar().study_days_only(timetable);
+    assert_eq!(timetable, HashMap::from([(Day::Tuesday, vec![tuesday])]));
+}
+
+#[test]
+fn rejects_invalid_periods() {
+    let overlapping = r#"{"semesters": [
+        {"name": "A", "start": "2026-09-01", "end": "2027-01-31"},
+        {"name": "B", "start": "2027-01-31", "end": "2027-06-30"}
+    ]}"#;
+    assert!(AcademicCalendar::parse(overlapping).is_err());
+
+    let reversed = r#"{"holidays": [{"name": "A", "start": "2026-11-04", "end": "2026-11-03"}]}"#;
+    assert!(AcademicCalendar::parse(reversed).is_err());
+}
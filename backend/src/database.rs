@@ -1,29 +1,34 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use diesel::connection::DefaultLoadingMode;
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
+use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel_migrations::MigrationHarness;
 use dotenvy::dotenv;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::error::Error;
 use std::fmt::Display;
 
+pub mod connection;
+use connection::{
+    insert_or_ignore, insert_returning_ids, on_backend, upsert, AnyConnection, ConnectionCustomizer,
+};
+pub mod diff;
+use diff::TimetableDiff;
 pub mod models;
 use models::*;
 pub mod schema;
 
 macro_rules! update_table {
-    ($conn:expr, $table:expr, $aggregate:expr) => {{
-        match diesel::insert_or_ignore_into($table)
-            .values($aggregate)
-            .execute($conn)
-        {
-            Ok(_) => {
+    ($conn:expr, $table:expr, $aggregate:expr, $key:expr, $columns:tt) => {{
+        match upsert!($conn, $table, $aggregate, $key, $columns) {
+            Ok(changed) => {
                 log::debug!(
-                    "Added {} new entries into table '{:?}'",
-                    $aggregate.len(),
+                    "Added or renamed {} entries in table '{:?}'",
+                    changed,
                     $table
                 );
-                Ok(())
+                Ok(changed)
             }
             Err(msg) => {
                 log::error!(
@@ -42,21 +47,22 @@ macro_rules! update_table {
 
 macro_rules! get_filtered_table_vec_data {
     ($conn:expr, $table:expr, $output:ident, ($key_type:ident, $key_field:ident), [$( ( $filter:ident, $val:expr ) ),*]) => {{
-        match $table.filter(
+        match $table
             $(
-                $filter.eq($val)
+                .filter($filter.eq($val))
             )*
-        ).load_iter::<$output, DefaultLoadingMode>($conn) {
-            Ok(query_res) => {
-                let output = query_res.fold(HashMap::new(), |mut map: HashMap<$key_type, Vec<$output>>, el| {
-                    let el = el.unwrap();
+            .load_iter::<$output, DefaultLoadingMode>($conn) {
+            Ok(mut query_res) => {
+                query_res.try_fold(HashMap::new(), |mut map: HashMap<$key_type, Vec<$output>>, el| {
+                    let el = el.map_err(|msg| {
+                        log::error!("Error: '{}' while reading an entry of table '{:?}'", msg, $table);
+                        DBError::RetrieveError(format!("Could not read data from '{:?}'", $table))
+                    })?;
                     map.entry(el.$key_field.clone())
                         .and_modify(|list| list.push(el.clone()))
                         .or_insert_with(|| vec![el]);
-                    map
-                });
-
-                Ok(output)
+                    Ok(map)
+                })
             }
             Err(msg) => {
 
@@ -87,11 +93,17 @@ macro_rules! get_filtered_table_vec_data {
 pub enum DBError {
     UpdateError(String),
     RetrieveError(String),
+    /// No database connection could be obtained
+    Unavailable(String),
 }
 
 impl Display for DBError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        match self {
+            Self::UpdateError(msg) | Self::RetrieveError(msg) | Self::Unavailable(msg) => {
+                write!(f, "{msg}")
+            }
+        }
     }
 }
 
@@ -99,39 +111,104 @@ impl Error for DBError {}
 
 pub type DBResult<T> = Result<T, DBError>;
 
+pub type DBConnection = PooledConnection<ConnectionManager<AnyConnection>>;
+
+/// Handle to the database backed by a connection pool, cheap to clone
+#[derive(Clone)]
 pub struct Database {
-    pub conn: SqliteConnection,
+    pool: Pool<ConnectionManager<AnyConnection>>,
 }
 
 impl Database {
-    pub fn new() -> Self {
+    /// Connects to the database at `DATABASE_URL`
+    pub fn from_env() -> DBResult<Self> {
         dotenv().ok();
 
-        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let mut conn = SqliteConnection::establish(&database_url)
-            .unwrap_or_else(|_| panic!("Error connecting to {database_url}"));
-        match diesel::sql_query("PRAGMA foreign_keys = ON").execute(&mut conn) {
-            Ok(_) => log::info!("Activated foreign keys in database"),
-            Err(e) => {
-                log::error!("Could not activate foreigh keys in database: '{e}'")
-            }
-        };
-        Self { conn }
+        let database_url = env::var("DATABASE_URL")
+            .map_err(|_| DBError::Unavailable(String::from("DATABASE_URL must be set")))?;
+        Self::connect(&database_url)
+            .map_err(|e| DBError::Unavailable(format!("Error connecting to {database_url}: {e}")))
     }
 
-    pub fn update_faculties(&mut self, new_faculties: &Vec<Faculty>) -> DBResult<()> {
-        update_table!(
-            &mut self.conn,
-            schema::faculties::dsl::faculties,
-            new_faculties
+    /// Connects to PostgreSQL if the URL starts with `postgres://` or `postgresql://`
+    /// and the `postgres` feature is enabled, to the SQLite database at the path otherwise
+    pub fn connect(database_url: &str) -> DBResult<Self> {
+        let is_postgres = ["postgres://", "postgresql://"]
+            .iter()
+            .any(|scheme| database_url.starts_with(scheme));
+        if is_postgres && cfg!(not(feature = "postgres")) {
+            return Err(DBError::Unavailable(String::from(
+                "PostgreSQL is not supported by this build, enable the `postgres` feature",
+            )));
+        }
+
+        let pool = Pool::builder()
+            .connection_customizer(Box::new(ConnectionCustomizer))
+            .build(ConnectionManager::<AnyConnection>::new(database_url))
+            .map_err(|e| DBError::Unavailable(e.to_string()))?;
+        let db = Self { pool };
+        log::info!("Connected to {} database", db.conn()?.backend_name());
+        Ok(db)
+    }
+
+    /// Takes a connection from the pool, fails if none becomes available within the pool timeout
+    pub fn conn(&self) -> DBResult<DBConnection> {
+        self.pool.get().map_err(|e| {
+            log::error!("Error: '{e}' while getting a database connection from the pool");
+            DBError::Unavailable(String::from("Database is unavailable"))
+        })
+    }
+
+    /// Applies migrations which were not applied yet, returns versions of the applied ones
+    pub fn run_migrations(&self) -> DBResult<Vec<String>> {
+        let conn = &mut self.conn()?;
+        let migrations = conn.migrations();
+        conn.run_pending_migrations(migrations)
+            .map(|versions| versions.iter().map(ToString::to_string).collect())
+            .map_err(|e| {
+                log::error!("Error: '{e}' while running migrations");
+                DBError::UpdateError(String::from("Could not run migrations"))
+            })
+    }
+
+    pub fn get_schema_version(&self) -> DBResult<SchemaVersion> {
+        let conn = &mut self.conn()?;
+        let embedded = conn.migrations();
+        let migrations = conn
+            .applied_migrations()
+            .and_then(|applied| Ok((applied, conn.pending_migrations(embedded)?)));
+        let (applied, pending) = migrations.map_err(|e| {
+            log::error!("Error: '{e}' while retrieving applied migrations");
+            DBError::RetrieveError(String::from("Could not retreive applied migrations"))
+        })?;
+        Ok(SchemaVersion {
+            version: applied.iter().max().map(ToString::to_string),
+            pending: pending
+                .iter()
+                .map(|migration| migration.name().version().to_string())
+                .collect(),
+        })
+    }
+
+    /// Stores new faculties and renames the stored ones. Faculties which are no longer
+    /// on the website are kept, their groups and timetables stay available
+    pub fn update_faculties(&self, new_faculties: &[Faculty]) -> DBResult<()> {
+        use schema::faculties::dsl::*;
+        let conn = &mut self.conn()?;
+        let changed = update_table!(conn, faculties, new_faculties, uuid, (name))?;
+        record_scrape(
+            conn,
+            Resource::Faculties,
+            chrono::Utc::now().naive_utc(),
+            changed > 0,
         )
     }
 
     /// Returns all current faculties of the RUDN university
     /// If the vector is empty, something is wrong with the database
-    pub fn get_faculties(&mut self) -> DBResult<Vec<Faculty>> {
+    pub fn get_faculties(&self) -> DBResult<Vec<Faculty>> {
         use schema::faculties::dsl::*;
-        faculties.load::<Faculty>(&mut self.conn).map_err(|e| {
+        faculties.load::<Faculty>(&mut self.conn()?).map_err(|e| {
             log::error!("Error: '{e}' while retrieving faculties from the database");
             DBError::RetrieveError(String::from(
                 "Could not retreive faculties from the database",
@@ -139,17 +216,67 @@ impl Database {
         })
     }
 
-    pub fn update_groups(&mut self, new_groups: &Vec<Group>) -> DBResult<()> {
-        update_table!(&mut self.conn, schema::groups::dsl::groups, new_groups)
+    /// Stores new groups and renames or moves the stored ones between faculties.
+    /// Groups which are no longer on the website are kept with their timetables and changes
+    pub fn update_groups(&self, new_groups: &[Group]) -> DBResult<()> {
+        let mut by_faculty: HashMap<&Uuid, Vec<Group>> = HashMap::new();
+        for group in new_groups {
+            by_faculty
+                .entry(&group.faculty)
+                .or_default()
+                .push(group.clone());
+        }
+
+        let conn = &mut self.conn()?;
+        let now = chrono::Utc::now().naive_utc();
+        for (faculty_uuid, new_groups) in by_faculty {
+            use schema::groups::dsl::*;
+            let changed = update_table!(conn, groups, &new_groups, uuid, (name, faculty))?;
+            record_scrape(conn, Resource::Groups(faculty_uuid), now, changed > 0)?;
+        }
+        Ok(())
+    }
+
+    /// Returns student groups of every faculty stored in the database
+    pub fn get_groups(&self) -> DBResult<Vec<Group>> {
+        use schema::groups::dsl::*;
+        groups.load::<Group>(&mut self.conn()?).map_err(|e| {
+            log::error!("Error: '{e}' while retrieving groups from the database");
+            DBError::RetrieveError(String::from("Could not retreive groups from the database"))
+        })
+    }
+
+    /// Returns every stored group along with its faculty, if the faculty is stored as well
+    pub fn get_groups_with_faculties(&self) -> DBResult<Vec<(Group, Option<Faculty>)>> {
+        use schema::{faculties, groups};
+        groups::table
+            .left_join(faculties::table)
+            .load::<(Group, Option<Faculty>)>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving groups with their faculties");
+                DBError::RetrieveError(String::from("Could not retreive groups from the database"))
+            })
+    }
+
+    pub fn get_group(&self, group_uuid: &Uuid) -> DBResult<Option<Group>> {
+        use schema::groups::dsl::*;
+        groups
+            .find(group_uuid)
+            .first::<Group>(&mut self.conn()?)
+            .optional()
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving group {group_uuid}");
+                DBError::RetrieveError(format!("Could not retreive group {group_uuid}"))
+            })
     }
 
     pub fn get_groups_for_faculty(
-        &mut self,
+        &self,
         faculty_uuid: &Uuid,
     ) -> DBResult<HashMap<Uuid, Vec<Group>>> {
         use schema::groups::dsl::*;
         get_filtered_table_vec_data!(
-            &mut self.conn,
+            &mut self.conn()?,
             groups,
             Group,
             (Uuid, faculty),
@@ -157,26 +284,808 @@ impl Database {
         )
     }
 
-    pub fn get_timetable_for_group(&mut self, group: &Uuid) -> DBResult<HashMap<Day, Vec<Event>>> {
+    /// Returns the timetable of the group for the week starting on `week`
+    pub fn get_timetable_for_group(
+        &self,
+        group: &Uuid,
+        week: &NaiveDate,
+    ) -> DBResult<HashMap<Day, Vec<Event>>> {
         use schema::timetables::dsl::*;
         get_filtered_table_vec_data!(
-            &mut self.conn,
+            &mut self.conn()?,
             timetables,
             Event,
             (Day, day),
-            [(student_group, group)]
+            [(student_group, group), (week_start, week)]
         )
     }
 
-    pub fn update_timetable(&mut self, timetable: &HashMap<Day, Vec<Event>>) -> DBResult<()> {
-        update_table!(
-            &mut self.conn,
-            schema::timetables::dsl::timetables,
-            timetable
-                .iter()
-                .flat_map(|(_, v)| v.clone())
-                .map(InsertableEvent::from)
-                .collect::<Vec<_>>()
+    /// Returns events of the group for every stored week
+    pub fn get_events_for_group(&self, group: &Uuid) -> DBResult<Vec<Event>> {
+        use schema::timetables::dsl::*;
+        timetables
+            .filter(student_group.eq(group))
+            .load::<Event>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving events of group {group}");
+                DBError::RetrieveError(format!("Could not retreive events of group {group}"))
+            })
+    }
+
+    /// Applies a freshly scraped timetable in a single transaction: new events are inserted,
+    /// disappeared ones are deleted and moved ones are updated in place.
+    /// Every applied change is recorded in the `timetable_changes` table and delivered
+    /// to the subscribed webhooks, unless the group had no stored events yet.
+    /// The timetable is marked as scraped even when nothing changed
+    pub fn update_timetable(&self, timetable: &ScrapedTimetable) -> DBResult<TimetableDiff> {
+        use schema::timetable_changes::dsl::timetable_changes;
+        use schema::timetables::dsl::*;
+
+        let group = &timetable.group;
+        let mut new_rooms = timetable
+            .events()
+            .filter_map(|event| event.room.as_deref())
+            .collect::<Vec<_>>();
+        new_rooms.sort_unstable();
+        new_rooms.dedup();
+        let new_rooms = new_rooms.into_iter().map(Room::new).collect::<Vec<_>>();
+
+        let changed_at = chrono::Utc::now().naive_utc();
+        self.conn()?
+            .transaction(|conn| {
+                insert_or_ignore!(conn, schema::rooms::table, &new_rooms)?;
+
+                let stored = timetables
+                    .filter(student_group.eq(group))
+                    .load::<StoredEvent>(conn)?;
+                // Every class of the first scrape is new, webhooks are not flooded with them
+                let first_scrape = stored.is_empty();
+                let diff = TimetableDiff::between(stored, &timetable.weeks);
+
+                let mut changes = vec![];
+                for event in &diff.added {
+                    diesel::insert_into(timetables)
+                        .values(InsertableEvent::from(event.clone()))
+                        .execute(conn)?;
+                    changes.push(InsertableChange::new(
+                        ChangeKind::Added,
+                        None,
+                        Some(event),
+                        changed_at,
+                    ));
+                }
+                for stored in &diff.removed {
+                    diesel::delete(timetables.find(stored.id)).execute(conn)?;
+                    changes.push(InsertableChange::new(
+                        ChangeKind::Removed,
+                        Some(&stored.event),
+                        None,
+                        changed_at,
+                    ));
+                }
+                for (stored, event) in &diff.moved {
+                    diesel::update(timetables.find(stored.id))
+                        .set(InsertableEvent::from(event.clone()))
+                        .execute(conn)?;
+                    changes.push(InsertableChange::new(
+                        ChangeKind::Moved,
+                        Some(&stored.event),
+                        Some(event),
+                        changed_at,
+                    ));
+                }
+                if !changes.is_empty() {
+                    let change_ids = insert_returning_ids!(
+                        conn,
+                        timetable_changes,
+                        &changes,
+                        schema::timetable_changes::id
+                    )?;
+                    if !first_scrape {
+                        enqueue_deliveries(conn, group, &change_ids, &changes[0].changed_at)?;
+                    }
+                    link_instructors(conn, group)?;
+                    touch(conn, Resource::Timetable(group), changed_at)?;
+                }
+                mark_scraped(conn, Resource::Timetable(group), changed_at)?;
+
+                log::debug!(
+                    "Timetable of group {group}: {} added, {} removed, {} moved",
+                    diff.added.len(),
+                    diff.removed.len(),
+                    diff.moved.len()
+                );
+                Ok(diff)
+            })
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Error: '{e}' while applying timetable changes");
+                DBError::UpdateError(String::from("Could not apply timetable changes"))
+            })
+    }
+
+    /// Returns every instructor found in the stored timetables, ordered by name
+    pub fn get_instructors(&self) -> DBResult<Vec<Instructor>> {
+        use schema::instructors::dsl::*;
+        instructors
+            .order(name)
+            .load::<Instructor>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving instructors from the database");
+                DBError::RetrieveError(String::from(
+                    "Could not retreive instructors from the database",
+                ))
+            })
+    }
+
+    pub fn get_instructor(&self, instructor_id: i32) -> DBResult<Option<Instructor>> {
+        use schema::instructors::dsl::*;
+        instructors
+            .find(instructor_id)
+            .first::<Instructor>(&mut self.conn()?)
+            .optional()
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving instructor {instructor_id}");
+                DBError::RetrieveError(format!("Could not retreive instructor {instructor_id}"))
+            })
+    }
+
+    /// Returns events of every group taught by the instructor,
+    /// only those of the week starting on `week` if it is given
+    pub fn get_events_for_instructor(
+        &self,
+        instructor_id: i32,
+        week: Option<&NaiveDate>,
+    ) -> DBResult<Vec<Event>> {
+        use schema::timetables::dsl::*;
+        let mut query = timetables.filter(instructor.eq(instructor_id)).into_boxed();
+        if let Some(week) = week {
+            query = query.filter(week_start.eq(week));
+        }
+        query.load::<Event>(&mut self.conn()?).map_err(|e| {
+            log::error!("Error: '{e}' while retrieving events of instructor {instructor_id}");
+            DBError::RetrieveError(format!(
+                "Could not retreive events of instructor {instructor_id}"
+            ))
+        })
+    }
+
+    pub fn get_room(&self, room_name: &str) -> DBResult<Option<Room>> {
+        use schema::rooms::dsl::*;
+        rooms
+            .find(room_name)
+            .first::<Room>(&mut self.conn()?)
+            .optional()
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving room {room_name}");
+                DBError::RetrieveError(format!("Could not retreive room {room_name}"))
+            })
+    }
+
+    /// Returns rooms without any class of any group overlapping the time span
+    /// on the given day of the week starting on `week`, ordered by name
+    pub fn get_free_rooms(
+        &self,
+        week: &NaiveDate,
+        on: Day,
+        from: NaiveTime,
+        to: NaiveTime,
+    ) -> DBResult<Vec<Room>> {
+        use schema::rooms::dsl::*;
+        use schema::timetables;
+
+        let occupied = timetables::table
+            .select(timetables::room.assume_not_null())
+            .filter(timetables::room.is_not_null())
+            .filter(timetables::week_start.eq(week))
+            .filter(timetables::day.eq(on))
+            .filter(timetables::start_time.lt(to))
+            .filter(timetables::end_time.gt(from));
+        rooms
+            .filter(name.ne_all(occupied))
+            .order(name)
+            .load::<Room>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving free rooms");
+                DBError::RetrieveError(String::from("Could not retreive free rooms"))
+            })
+    }
+
+    /// Returns events of every group taking place in the room during the week starting on `week`
+    pub fn get_events_in_room(&self, room_name: &str, week: &NaiveDate) -> DBResult<Vec<Event>> {
+        use schema::timetables::dsl::*;
+        timetables
+            .filter(room.eq(room_name))
+            .filter(week_start.eq(week))
+            .load::<Event>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving events in room {room_name}");
+                DBError::RetrieveError(format!("Could not retreive events in room {room_name}"))
+            })
+    }
+
+    /// Returns when the resource was modified the last time, if it was ever stored
+    pub fn get_last_modified(&self, resource: Resource) -> DBResult<Option<NaiveDateTime>> {
+        use schema::last_modified::dsl;
+        let modified_at = dsl::last_modified
+            .find(resource.key())
+            .select(dsl::modified_at)
+            .first::<String>(&mut self.conn()?)
+            .optional()
+            .map_err(|e| {
+                log::error!(
+                    "Error: '{e}' while retrieving modification time of {}",
+                    resource.key()
+                );
+                DBError::RetrieveError(format!(
+                    "Could not retreive modification time of {}",
+                    resource.key()
+                ))
+            })?;
+        Ok(modified_at.and_then(|modified_at| {
+            NaiveDateTime::parse_from_str(&modified_at, "%Y-%m-%dT%H:%M:%S").ok()
+        }))
+    }
+
+    /// Returns when the resource was scraped the last time, if it was ever scraped
+    pub fn get_scraped_at(&self, resource: Resource) -> DBResult<Option<NaiveDateTime>> {
+        use schema::last_modified::dsl;
+        let scraped_at = dsl::last_modified
+            .find(resource.key())
+            .select(dsl::scraped_at)
+            .first::<Option<String>>(&mut self.conn()?)
+            .optional()
+            .map_err(|e| {
+                log::error!(
+                    "Error: '{e}' while retrieving scrape time of {}",
+                    resource.key()
+                );
+                DBError::RetrieveError(format!(
+                    "Could not retreive scrape time of {}",
+                    resource.key()
+                ))
+            })?;
+        Ok(scraped_at.flatten().and_then(|scraped_at| {
+            NaiveDateTime::parse_from_str(&scraped_at, "%Y-%m-%dT%H:%M:%S").ok()
+        }))
+    }
+
+    /// Returns recorded changes of the group's timetable, optionally only those made after `since`
+    pub fn get_changes_for_group(
+        &self,
+        group: &Uuid,
+        since: Option<NaiveDateTime>,
+    ) -> DBResult<Vec<TimetableChange>> {
+        use schema::timetable_changes::dsl::*;
+        let since = since
+            .map(|since| since.format("%Y-%m-%dT%H:%M:%S").to_string())
+            .unwrap_or_default();
+        timetable_changes
+            .filter(student_group.eq(group))
+            .filter(changed_at.gt(since))
+            .order(id)
+            .load::<TimetableChange>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving changes of group {group}");
+                DBError::RetrieveError(format!("Could not retreive changes of group {group}"))
+            })
+    }
+
+    pub fn add_webhook(&self, webhook: &NewWebhook) -> DBResult<Webhook> {
+        use schema::webhooks::dsl::*;
+        self.conn()?
+            .transaction(|conn| {
+                diesel::insert_into(webhooks)
+                    .values(webhook)
+                    .execute(conn)?;
+                webhooks.order(id.desc()).first::<Webhook>(conn)
+            })
+            .map_err(|e| {
+                log::error!("Error: '{e}' while adding webhook for {}", webhook.url);
+                DBError::UpdateError(String::from("Could not add webhook"))
+            })
+    }
+
+    /// Deletes the webhook with its pending deliveries, returns whether it existed
+    pub fn delete_webhook(&self, webhook_id: i32) -> DBResult<bool> {
+        use schema::webhooks::dsl::*;
+        diesel::delete(webhooks.find(webhook_id))
+            .execute(&mut self.conn()?)
+            .map(|deleted| deleted > 0)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while deleting webhook {webhook_id}");
+                DBError::UpdateError(format!("Could not delete webhook {webhook_id}"))
+            })
+    }
+
+    /// Returns pending deliveries whose next attempt is due at `now`
+    pub fn get_due_deliveries(
+        &self,
+        now: NaiveDateTime,
+        limit: i64,
+    ) -> DBResult<Vec<(WebhookDelivery, Webhook, TimetableChange)>> {
+        use schema::webhook_deliveries::dsl::*;
+        let pending = serde_json::to_string(&DeliveryStatus::Pending).unwrap();
+        webhook_deliveries
+            .inner_join(schema::webhooks::table)
+            .inner_join(schema::timetable_changes::table)
+            .filter(status.eq(pending))
+            .filter(next_attempt_at.le(now.format("%Y-%m-%dT%H:%M:%S").to_string()))
+            .order(id)
+            .limit(limit)
+            .load::<(WebhookDelivery, Webhook, TimetableChange)>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving due webhook deliveries");
+                DBError::RetrieveError(String::from("Could not retreive webhook deliveries"))
+            })
+    }
+
+    /// Returns deliveries which exhausted their attempts, the most recent first
+    pub fn get_dead_deliveries(&self) -> DBResult<Vec<WebhookDelivery>> {
+        use schema::webhook_deliveries::dsl::*;
+        let dead = serde_json::to_string(&DeliveryStatus::Dead).unwrap();
+        webhook_deliveries
+            .filter(status.eq(dead))
+            .order(id.desc())
+            .load::<WebhookDelivery>(&mut self.conn()?)
+            .map_err(|e| {
+                log::error!("Error: '{e}' while retrieving dead webhook deliveries");
+                DBError::RetrieveError(String::from("Could not retreive webhook deliveries"))
+            })
+    }
+
+    /// Stores the outcome of a delivery attempt
+    pub fn update_delivery(
+        &self,
+        delivery_id: i32,
+        new_status: DeliveryStatus,
+        new_next_attempt_at: NaiveDateTime,
+        error: Option<String>,
+    ) -> DBResult<()> {
+        use schema::webhook_deliveries::dsl::*;
+        diesel::update(webhook_deliveries.find(delivery_id))
+            .set((
+                status.eq(serde_json::to_string(&new_status).unwrap()),
+                attempts.eq(attempts + 1),
+                next_attempt_at.eq(new_next_attempt_at.format("%Y-%m-%dT%H:%M:%S").to_string()),
+                last_error.eq(error),
+            ))
+            .execute(&mut self.conn()?)
+            .map(|_| ())
+            .map_err(|e| {
+                log::error!("Error: '{e}' while updating webhook delivery {delivery_id}");
+                DBError::UpdateError(format!("Could not update webhook delivery {delivery_id}"))
+            })
+    }
+
+    /// Counts the stored rows of every kind
+    pub fn get_stats(&self) -> DBResult<DatabaseStats> {
+        use schema::*;
+        let pending = serde_json::to_string(&DeliveryStatus::Pending).unwrap();
+        let dead = serde_json::to_string(&DeliveryStatus::Dead).unwrap();
+        self.conn()?
+            .transaction(|conn| {
+                let first_week = timetables::table
+                    .select(diesel::dsl::min(timetables::week_start))
+                    .first::<Option<NaiveDate>>(conn)?;
+                let last_week = timetables::table
+                    .select(diesel::dsl::max(timetables::week_start))
+                    .first::<Option<NaiveDate>>(conn)?;
+                let oldest_scrape = last_modified::table
+                    .select(diesel::dsl::min(last_modified::scraped_at))
+                    .first::<Option<String>>(conn)?;
+                Ok(DatabaseStats {
+                    faculties: faculties::table.count().get_result(conn)?,
+                    groups: groups::table.count().get_result(conn)?,
+                    events: timetables::table.count().get_result(conn)?,
+                    instructors: instructors::table.count().get_result(conn)?,
+                    rooms: rooms::table.count().get_result(conn)?,
+                    changes: timetable_changes::table.count().get_result(conn)?,
+                    webhooks: webhooks::table.count().get_result(conn)?,
+                    pending_deliveries: webhook_deliveries::table
+                        .filter(webhook_deliveries::status.eq(&pending))
+                        .count()
+                        .get_result(conn)?,
+                    dead_deliveries: webhook_deliveries::table
+                        .filter(webhook_deliveries::status.eq(&dead))
+                        .count()
+                        .get_result(conn)?,
+                    weeks: first_week.zip(last_week),
+                    oldest_scrape: oldest_scrape.and_then(|scraped_at| {
+                        NaiveDateTime::parse_from_str(&scraped_at, "%Y-%m-%dT%H:%M:%S").ok()
+                    }),
+                })
+            })
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Error: '{e}' while counting stored data");
+                DBError::RetrieveError(String::from("Could not count stored data"))
+            })
+    }
+
+    /// Deletes events of the weeks starting before `before` and timetable changes
+    /// recorded before it along with their webhook deliveries
+    pub fn prune(&self, before: &NaiveDate) -> DBResult<PruneStats> {
+        use schema::{timetable_changes, timetables, webhook_deliveries};
+        let week = *before;
+        let before = before.format("%Y-%m-%d").to_string();
+        self.conn()?
+            .transaction(|conn| {
+                // Times of the changes start with the date, so they compare correctly as text
+                let old_changes = timetable_changes::table
+                    .select(timetable_changes::id)
+                    .filter(timetable_changes::changed_at.lt(&before));
+                let deliveries = diesel::delete(
+                    webhook_deliveries::table
+                        .filter(webhook_deliveries::change.eq_any(old_changes)),
+                )
+                .execute(conn)?;
+                let changes = diesel::delete(
+                    timetable_changes::table.filter(timetable_changes::changed_at.lt(&before)),
+                )
+                .execute(conn)?;
+                let events =
+                    diesel::delete(timetables::table.filter(timetables::week_start.lt(week)))
+                        .execute(conn)?;
+                Ok(PruneStats {
+                    events,
+                    changes,
+                    deliveries,
+                })
+            })
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Error: '{e}' while pruning data stored before {before}");
+                DBError::UpdateError(format!("Could not prune data stored before {before}"))
+            })
+    }
+
+    /// Returns every stored faculty, group and event, read in a single transaction
+    pub fn export(&self) -> DBResult<Bundle> {
+        use schema::{faculties, groups, timetables};
+        let schema_version = self.get_schema_version()?.version;
+        let (faculties, groups, timetables) = self
+            .conn()?
+            .transaction(|conn| {
+                Ok((
+                    faculties::table
+                        .order(faculties::uuid)
+                        .load::<Faculty>(conn)?,
+                    groups::table.order(groups::uuid).load::<Group>(conn)?,
+                    timetables::table
+                        .order((
+                            timetables::student_group,
+                            timetables::week_start,
+                            timetables::id,
+                        ))
+                        .load::<Event>(conn)?,
+                ))
+            })
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Error: '{e}' while exporting the database");
+                DBError::RetrieveError(String::from("Could not export the database"))
+            })?;
+        Ok(Bundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            metadata: BundleMetadata {
+                exported_at: chrono::Utc::now().naive_utc(),
+                backend_version: env!("CARGO_PKG_VERSION").to_string(),
+                schema_version,
+            },
+            faculties,
+            groups,
+            timetables,
+        })
+    }
+
+    /// Imports the bundle in a single transaction. Stored entities are kept as they are,
+    /// those differing from the imported ones are reported as conflicts, e.g. a stored week
+    /// of a group's timetable is never mixed with the imported one.
+    /// Nothing is imported if a group or an event refers to a faculty or a group
+    /// which is neither imported nor stored
+    pub fn import(&self, bundle: &Bundle) -> DBResult<ImportReport> {
+        use schema::{faculties, groups, timetables};
+        let imported_at = chrono::Utc::now().naive_utc();
+        self.conn()?
+            .transaction(|conn| {
+                let stored_faculties = faculties::table.load::<Faculty>(conn)?;
+                let stored_groups = groups::table.load::<Group>(conn)?;
+                let mut report = ImportReport::default();
+
+                let known_faculties = stored_faculties
+                    .iter()
+                    .chain(&bundle.faculties)
+                    .map(|faculty| &faculty.uuid)
+                    .collect::<HashSet<_>>();
+                for group in &bundle.groups {
+                    if !known_faculties.contains(&group.faculty) {
+                        report.invalid.push(format!(
+                            "Group {} refers to unknown faculty {}",
+                            group.uuid, group.faculty
+                        ));
+                    }
+                }
+                let known_groups = stored_groups
+                    .iter()
+                    .chain(&bundle.groups)
+                    .map(|group| &group.uuid)
+                    .collect::<HashSet<_>>();
+                let mut unknown_groups = bundle
+                    .timetables
+                    .iter()
+                    .map(|event| &event.student_group)
+                    .filter(|group| !known_groups.contains(group))
+                    .collect::<Vec<_>>();
+                unknown_groups.sort_unstable();
+                unknown_groups.dedup();
+                for group in unknown_groups {
+                    report
+                        .invalid
+                        .push(format!("Events refer to unknown group {group}"));
+                }
+                if !report.invalid.is_empty() {
+                    return Ok(report);
+                }
+
+                // Names are unique as well, so an entity may conflict with another stored one
+                let mut new_faculties = vec![];
+                for faculty in &bundle.faculties {
+                    match stored_faculties
+                        .iter()
+                        .find(|stored| stored.uuid == faculty.uuid || stored.name == faculty.name)
+                    {
+                        Some(stored) if stored.uuid == faculty.uuid && stored.name == faculty.name => {}
+                        Some(stored) => report.conflicts.push(format!(
+                            "Faculty {} \"{}\" conflicts with stored faculty {} \"{}\"",
+                            faculty.uuid, faculty.name, stored.uuid, stored.name
+                        )),
+                        None => new_faculties.push(faculty.clone()),
+                    }
+                }
+                report.faculties = insert_or_ignore!(conn, faculties::table, &new_faculties)?;
+                if report.faculties > 0 {
+                    touch(conn, Resource::Faculties, imported_at)?;
+                }
+
+                // Entities referring to a conflicting one are skipped along with it
+                let available_faculties = stored_faculties
+                    .iter()
+                    .chain(&new_faculties)
+                    .map(|faculty| &faculty.uuid)
+                    .collect::<HashSet<_>>();
+                let mut new_groups = vec![];
+                for group in &bundle.groups {
+                    if !available_faculties.contains(&group.faculty) {
+                        report.conflicts.push(format!(
+                            "Group {} is skipped as its faculty {} conflicts with a stored one",
+                            group.uuid, group.faculty
+                        ));
+                        continue;
+                    }
+                    match stored_groups
+                        .iter()
+                        .find(|stored| stored.uuid == group.uuid || stored.name == group.name)
+                    {
+                        Some(stored)
+                            if stored.uuid == group.uuid
+                                && stored.name == group.name
+                                && stored.faculty == group.faculty => {}
+                        Some(stored) => report.conflicts.push(format!(
+                            "Group {} \"{}\" conflicts with stored group {} \"{}\"",
+                            group.uuid, group.name, stored.uuid, stored.name
+                        )),
+                        None => new_groups.push(group.clone()),
+                    }
+                }
+                report.groups = insert_or_ignore!(conn, groups::table, &new_groups)?;
+                let mut new_group_faculties = new_groups
+                    .iter()
+                    .map(|group| &group.faculty)
+                    .collect::<Vec<_>>();
+                new_group_faculties.sort_unstable();
+                new_group_faculties.dedup();
+                for faculty in new_group_faculties {
+                    touch(conn, Resource::Groups(faculty), imported_at)?;
+                }
+
+                let available_groups = stored_groups
+                    .iter()
+                    .chain(&new_groups)
+                    .map(|group| &group.uuid)
+                    .collect::<HashSet<_>>();
+                let mut weeks: BTreeMap<(&Uuid, NaiveDate), Vec<&Event>> = BTreeMap::new();
+                for event in &bundle.timetables {
+                    weeks
+                        .entry((&event.student_group, event.week_start))
+                        .or_default()
+                        .push(event);
+                }
+                let mut new_events: BTreeMap<&Uuid, Vec<InsertableEvent>> = BTreeMap::new();
+                for ((group, week), events) in weeks {
+                    if !available_groups.contains(group) {
+                        report.conflicts.push(format!(
+                            "Timetable of group {group} for the week of {week} is skipped \
+                            as the group conflicts with a stored one"
+                        ));
+                        continue;
+                    }
+                    let mut stored = timetables::table
+                        .filter(timetables::student_group.eq(group))
+                        .filter(timetables::week_start.eq(week))
+                        .load::<Event>(conn)?;
+                    if stored.is_empty() {
+                        new_events
+                            .entry(group)
+                            .or_default()
+                            .extend(events.into_iter().cloned().map(InsertableEvent::from));
+                        continue;
+                    }
+                    let mut unmatched = 0;
+                    for event in events {
+                        match stored.iter().position(|stored| stored == event) {
+                            Some(i) => {
+                                stored.swap_remove(i);
+                            }
+                            None => unmatched += 1,
+                        }
+                    }
+                    if unmatched > 0 || !stored.is_empty() {
+                        report.conflicts.push(format!(
+                            "Timetable of group {group} for the week of {week} differs from the stored one"
+                        ));
+                    }
+                }
+                for (group, events) in new_events {
+                    let rooms = events
+                        .iter()
+                        .filter_map(|event| event.room.as_deref())
+                        .collect::<HashSet<_>>()
+                        .into_iter()
+                        .map(Room::new)
+                        .collect::<Vec<_>>();
+                    insert_or_ignore!(conn, schema::rooms::table, &rooms)?;
+                    report.events += on_backend!(conn, |conn| diesel::insert_into(
+                        timetables::table
+                    )
+                    .values(&events)
+                    .execute(conn))?;
+                    link_instructors(conn, group)?;
+                    touch(conn, Resource::Timetable(group), imported_at)?;
+                }
+                Ok(report)
+            })
+            .map_err(|e: diesel::result::Error| {
+                log::error!("Error: '{e}' while importing a bundle");
+                DBError::UpdateError(String::from("Could not import the bundle"))
+            })
+    }
+}
+
+/// Records that the resource was modified at `modified_at`
+fn touch(
+    conn: &mut AnyConnection,
+    resource: Resource,
+    modified_at: NaiveDateTime,
+) -> QueryResult<usize> {
+    use schema::last_modified::dsl;
+    let modified_at = modified_at.format("%Y-%m-%dT%H:%M:%S").to_string();
+    on_backend!(conn, |conn| diesel::insert_into(dsl::last_modified)
+        .values((
+            dsl::resource.eq(resource.key()),
+            dsl::modified_at.eq(&modified_at),
+        ))
+        .on_conflict(dsl::resource)
+        .do_update()
+        .set(dsl::modified_at.eq(&modified_at))
+        .execute(conn))
+}
+
+/// Records that the resource was scraped at `scraped_at`,
+/// a resource seen for the first time is considered modified then as well
+fn mark_scraped(
+    conn: &mut AnyConnection,
+    resource: Resource,
+    scraped_at: NaiveDateTime,
+) -> QueryResult<usize> {
+    use schema::last_modified::dsl;
+    let scraped_at = scraped_at.format("%Y-%m-%dT%H:%M:%S").to_string();
+    on_backend!(conn, |conn| diesel::insert_into(dsl::last_modified)
+        .values((
+            dsl::resource.eq(resource.key()),
+            dsl::modified_at.eq(&scraped_at),
+            dsl::scraped_at.eq(&scraped_at),
+        ))
+        .on_conflict(dsl::resource)
+        .do_update()
+        .set(dsl::scraped_at.eq(&scraped_at))
+        .execute(conn))
+}
+
+/// Records a scrape of the resource, which changed the stored data if `modified` is set
+fn record_scrape(
+    conn: &mut AnyConnection,
+    resource: Resource,
+    scraped_at: NaiveDateTime,
+    modified: bool,
+) -> DBResult<()> {
+    conn.transaction(|conn| {
+        if modified {
+            touch(conn, resource, scraped_at)?;
+        }
+        mark_scraped(conn, resource, scraped_at)
+    })
+    .map(|_| ())
+    .map_err(|e: diesel::result::Error| {
+        log::error!("Error: '{e}' while recording scrape of {}", resource.key());
+        DBError::UpdateError(format!("Could not record scrape of {}", resource.key()))
+    })
+}
+
+/// Adds teachers of the group's events to the `instructors` table and links the events to them
+fn link_instructors(conn: &mut AnyConnection, group: &Uuid) -> QueryResult<usize> {
+    use schema::{instructors, timetables};
+    let teachers = timetables::table
+        .select(timetables::teacher.assume_not_null())
+        .filter(timetables::student_group.eq(group))
+        .filter(timetables::teacher.is_not_null())
+        .distinct()
+        .load::<String>(conn)?;
+    let new_instructors = teachers
+        .iter()
+        .map(|teacher| instructors::name.eq(teacher))
+        .collect::<Vec<_>>();
+    insert_or_ignore!(conn, instructors::table, &new_instructors)?;
+
+    let mut linked = 0;
+    for instructor in instructors::table
+        .filter(instructors::name.eq_any(&teachers))
+        .load::<Instructor>(conn)?
+    {
+        linked += diesel::update(
+            timetables::table
+                .filter(timetables::student_group.eq(group))
+                .filter(timetables::teacher.eq(&instructor.name)),
         )
+        .set(timetables::instructor.eq(instructor.id))
+        .execute(conn)?;
     }
+    Ok(linked)
 }
+
+/// Schedules delivery of the group's `changes` recorded at `changed_at` to every subscribed webhook
+fn enqueue_deliveries(
+    conn: &mut AnyConnection,
+    group: &Uuid,
+    changes: &[i32],
+    changed_at: &str,
+) -> QueryResult<usize> {
+    use schema::{webhook_deliveries, webhooks};
+    let subscribed = webhooks::table
+        .select(webhooks::id)
+        .filter(
+            webhooks::student_group
+                .is_null()
+                .or(webhooks::student_group.eq(group)),
+        )
+        .load::<i32>(conn)?;
+
+    let pending = serde_json::to_string(&DeliveryStatus::Pending).unwrap();
+    let deliveries = subscribed
+        .iter()
+        .flat_map(|&webhook| changes.iter().map(move |&change| (webhook, change)))
+        .map(|(webhook, change)| {
+            (
+                webhook_deliveries::webhook.eq(webhook),
+                webhook_deliveries::change.eq(change),
+                webhook_deliveries::status.eq(&pending),
+                webhook_deliveries::next_attempt_at.eq(changed_at),
+            )
+        })
+        .collect::<Vec<_>>();
+    if deliveries.is_empty() {
+        return Ok(0);
+    }
+    on_backend!(conn, |conn| diesel::insert_into(webhook_deliveries::table)
+        .values(&deliveries)
+        .execute(conn))
+}
+
+#[cfg(test)]
+mod tests;
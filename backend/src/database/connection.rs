@@ -0,0 +1,166 @@
+//! Connection to either of the supported databases, chosen by the database URL
+
+use diesel::prelude::*;
+use diesel::r2d2::CustomizeConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations};
+
+/// Migrations of every backend, compiled into the binary
+pub const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/sqlite");
+#[cfg(feature = "postgres")]
+pub const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/postgres");
+
+/// SQLite accepts any path, so it comes last to let PostgreSQL URLs reach PostgreSQL
+#[derive(diesel::MultiConnection)]
+pub enum AnyConnection {
+    #[cfg(feature = "postgres")]
+    Postgresql(diesel::PgConnection),
+    Sqlite(diesel::SqliteConnection),
+}
+
+/// Runs the query on the connection of the concrete backend, for queries which
+/// the common backend does not support, i.e. upserts and inserts of several rows
+macro_rules! on_backend {
+    ($conn:expr, |$inner:ident| $query:expr) => {{
+        let conn: &mut $crate::database::connection::AnyConnection = $conn;
+        match conn {
+            #[cfg(feature = "postgres")]
+            $crate::database::connection::AnyConnection::Postgresql($inner) => $query,
+            $crate::database::connection::AnyConnection::Sqlite($inner) => $query,
+        }
+    }};
+}
+pub(crate) use on_backend;
+
+/// Inserts the rows skipping those conflicting with the stored ones,
+/// SQLite supports it for a batch of rows only with its own syntax
+macro_rules! insert_or_ignore {
+    ($conn:expr, $table:expr, $values:expr) => {{
+        let conn: &mut $crate::database::connection::AnyConnection = $conn;
+        match conn {
+            #[cfg(feature = "postgres")]
+            $crate::database::connection::AnyConnection::Postgresql(conn) => {
+                diesel::insert_into($table)
+                    .values($values)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+            }
+            $crate::database::connection::AnyConnection::Sqlite(conn) => {
+                diesel::insert_or_ignore_into($table)
+                    .values($values)
+                    .execute(conn)
+            }
+        }
+    }};
+}
+pub(crate) use insert_or_ignore;
+
+/// Inserts the rows updating `columns` of the stored ones with the same `key`.
+/// Only rows whose columns differ are updated, so the count is of actually changed rows.
+/// SQLite does not support it for a batch of rows, so they are upserted one by one there
+macro_rules! upsert {
+    ($conn:expr, $table:expr, $values:expr, $key:expr, ($first:expr $(, $column:expr)* $(,)?)) => {{
+        #[allow(unused_imports)]
+        use diesel::query_dsl::methods::{FilterDsl, OrFilterDsl};
+        use diesel::upsert::excluded;
+        let conn: &mut $crate::database::connection::AnyConnection = $conn;
+        macro_rules! upsert_into {
+            ($conn_:expr, $values_:expr) => {
+                diesel::insert_into($table)
+                    .values($values_)
+                    .on_conflict($key)
+                    .do_update()
+                    .set(($first.eq(excluded($first)), $($column.eq(excluded($column)),)*))
+                    .filter($first.ne(excluded($first)))
+                    $(.or_filter($column.ne(excluded($column))))*
+                    .execute($conn_)
+            };
+        }
+        match conn {
+            #[cfg(feature = "postgres")]
+            $crate::database::connection::AnyConnection::Postgresql(conn) => {
+                upsert_into!(conn, $values)
+            }
+            $crate::database::connection::AnyConnection::Sqlite(conn) => {
+                $values.iter().try_fold(0, |changed, value| {
+                    upsert_into!(&mut *conn, value).map(|upserted| changed + upserted)
+                })
+            }
+        }
+    }};
+}
+pub(crate) use upsert;
+
+/// Inserts the rows returning `id` of every inserted row in the order of `values`.
+/// SQLite before 3.35 has no RETURNING, so the rows are inserted one by one there
+/// taking the id of each from `last_insert_rowid()`
+macro_rules! insert_returning_ids {
+    ($conn:expr, $table:expr, $values:expr, $id:expr) => {{
+        let conn: &mut $crate::database::connection::AnyConnection = $conn;
+        match conn {
+            #[cfg(feature = "postgres")]
+            $crate::database::connection::AnyConnection::Postgresql(conn) => {
+                diesel::insert_into($table)
+                    .values($values)
+                    .returning($id)
+                    .get_results::<i32>(conn)
+            }
+            $crate::database::connection::AnyConnection::Sqlite(conn) => $values
+                .iter()
+                .map(|value| {
+                    diesel::insert_into($table)
+                        .values(value)
+                        .execute(&mut *conn)?;
+                    diesel::select($crate::database::connection::last_insert_rowid())
+                        .get_result::<i32>(&mut *conn)
+                })
+                .collect::<QueryResult<Vec<_>>>(),
+        }
+    }};
+}
+pub(crate) use insert_returning_ids;
+
+diesel::define_sql_function! {
+    /// Rowid of the row inserted last by the SQLite connection
+    fn last_insert_rowid() -> diesel::sql_types::Integer;
+}
+
+impl AnyConnection {
+    pub fn backend_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgresql(_) => "PostgreSQL",
+            Self::Sqlite(_) => "SQLite",
+        }
+    }
+
+    /// Migrations written for the backend of the connection
+    pub fn migrations(&self) -> EmbeddedMigrations {
+        match self {
+            #[cfg(feature = "postgres")]
+            Self::Postgresql(_) => POSTGRES_MIGRATIONS,
+            Self::Sqlite(_) => SQLITE_MIGRATIONS,
+        }
+    }
+}
+
+/// Sets up every connection handed out by the pool
+#[derive(Debug)]
+pub struct ConnectionCustomizer;
+
+impl CustomizeConnection<AnyConnection, diesel::r2d2::Error> for ConnectionCustomizer {
+    fn on_acquire(&self, conn: &mut AnyConnection) -> Result<(), diesel::r2d2::Error> {
+        match conn {
+            #[cfg(feature = "postgres")]
+            AnyConnection::Postgresql(_) => Ok(()),
+            // Busy timeout makes concurrent writers wait for each other instead of failing,
+            // it comes first as switching a new database to WAL is a write too.
+            // WAL lets readers proceed while the scheduler writes scraped data
+            AnyConnection::Sqlite(conn) => diesel::sql_query("PRAGMA busy_timeout = 5000")
+                .execute(conn)
+                .and_then(|_| diesel::sql_query("PRAGMA journal_mode = WAL").execute(conn))
+                .and_then(|_| diesel::sql_query("PRAGMA foreign_keys = ON").execute(conn))
+                .map(|_| ())
+                .map_err(diesel::r2d2::Error::QueryError),
+        }
+    }
+}
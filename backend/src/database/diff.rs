@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+
+use super::models::*;
+
+/// Difference between the stored timetable of a group and a freshly scraped one
+#[derive(Debug, Default)]
+pub struct TimetableDiff {
+    pub added: Vec<Event>,
+    pub removed: Vec<StoredEvent>,
+    /// Stored events paired with their new state
+    pub moved: Vec<(StoredEvent, Event)>,
+}
+
+impl TimetableDiff {
+    /// Compares events of the same group week by week. Only the weeks present in `weeks`
+    /// are compared, so stored weeks which are no longer shown on the timetable webpage
+    /// are kept intact, while the classes of a scraped week without any are removed
+    pub fn between(stored: Vec<StoredEvent>, weeks: &HashMap<NaiveDate, Vec<Event>>) -> Self {
+        let mut diff = Self::default();
+
+        let mut stored = stored
+            .into_iter()
+            .filter(|stored| weeks.contains_key(&stored.event.week_start))
+            .collect::<Vec<_>>();
+
+        let mut unmatched = vec![];
+        for event in weeks.values().flatten() {
+            match stored.iter().position(|stored| &stored.event == event) {
+                Some(i) => {
+                    stored.swap_remove(i);
+                }
+                None => unmatched.push(event.clone()),
+            }
+        }
+
+        // The same class within the same week which changed its time, room or details.
+        // Closer occurrences are paired first, so that when several occurrences of a class
+        // within a week change at once, none of them is paired with another day
+        let closeness: [fn(&Event, &Event) -> bool; 3] = [
+            |a, b| a.day == b.day && a.start_time == b.start_time,
+            |a, b| a.day == b.day,
+            |_, _| true,
+        ];
+        for is_close in closeness {
+            let mut still_unmatched = vec![];
+            for event in unmatched {
+                match stored.iter().position(|stored| {
+                    is_same_class(&stored.event, &event) && is_close(&stored.event, &event)
+                }) {
+                    Some(i) => diff.moved.push((stored.swap_remove(i), event)),
+                    None => still_unmatched.push(event),
+                }
+            }
+            unmatched = still_unmatched;
+        }
+        diff.added = unmatched;
+        diff.removed = stored;
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+fn is_same_class(a: &Event, b: &Event) -> bool {
+    a.student_group == b.student_group
+        && a.week_start == b.week_start
+        && a.name == b.name
+        && a.kind == b.kind
+        && a.subgroup == b.subgroup
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,145 @@
+use chrono::NaiveTime;
+
+use super::*;
+
+fn week() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, 5).unwrap()
+}
+
+fn next_week() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, 12).unwrap()
+}
+
+fn stored(events: &[Event]) -> Vec<StoredEvent> {
+    events
+        .iter()
+        .enumerate()
+        .map(|(i, event)| StoredEvent {
+            id: i as i32 + 1,
+            event: event.clone(),
+        })
+        .collect()
+}
+
+fn weeks(weeks: &[(NaiveDate, &[Event])]) -> HashMap<NaiveDate, Vec<Event>> {
+    weeks
+        .iter()
+        .map(|(week_start, events)| (*week_start, events.to_vec()))
+        .collect()
+}
+
+fn ids(events: &[StoredEvent]) -> Vec<i32> {
+    events.iter().map(|stored| stored.id).collect()
+}
+
+#[test]
+fn unchanged_timetable_has_no_difference() {
+    let math = Event::builder().build();
+    let diff = TimetableDiff::between(
+        stored(std::slice::from_ref(&math)),
+        &weeks(&[(week(), &[math])]),
+    );
+    assert!(diff.is_empty());
+}
+
+#[test]
+fn changed_class_is_moved() {
+    let math = Event::builder().room("101").build();
+    let moved = Event::builder().starts_at(13).room("102").build();
+    let physics = Event::builder().name("Physics").day(Day::Friday).build();
+
+    let diff = TimetableDiff::between(
+        stored(&[math, physics.clone()]),
+        &weeks(&[(week(), &[moved.clone(), physics])]),
+    );
+    assert!(diff.added.is_empty());
+    assert!(diff.removed.is_empty());
+    assert_eq!(diff.moved.len(), 1);
+    assert_eq!(diff.moved[0].0.id, 1);
+    assert_eq!(diff.moved[0].1, moved);
+}
+
+#[test]
+fn other_classes_are_added_and_removed() {
+    let math = Event::builder().build();
+    let lecture = Event::builder().kind(EventKind::Lecture).build();
+    let physics = Event::builder().name("Physics").build();
+
+    let diff = TimetableDiff::between(
+        stored(&[math, physics]),
+        &weeks(&[(week(), std::slice::from_ref(&lecture))]),
+    );
+    assert_eq!(diff.added, vec![lecture]);
+    assert_eq!(ids(&diff.removed), [1, 2]);
+    assert!(diff.moved.is_empty());
+}
+
+#[test]
+fn classes_of_an_empty_week_are_removed() {
+    let math = Event::builder().build();
+    let next_math = Event::builder().week(next_week()).build();
+
+    let diff = TimetableDiff::between(stored(&[math, next_math]), &weeks(&[(week(), &[])]));
+    assert!(diff.added.is_empty());
+    assert_eq!(ids(&diff.removed), [1]);
+}
+
+#[test]
+fn weeks_which_were_not_scraped_are_kept() {
+    let math = Event::builder().build();
+    let next_math = Event::builder().week(next_week()).build();
+
+    let diff = TimetableDiff::between(
+        stored(&[math, next_math.clone()]),
+        &weeks(&[(next_week(), &[next_math])]),
+    );
+    assert!(diff.is_empty());
+    assert!(
+        TimetableDiff::between(stored(&[Event::builder().build()]), &HashMap::new()).is_empty()
+    );
+}
+
+#[test]
+fn same_class_on_several_days_is_paired_by_day() {
+    let monday = Event::builder().room("101").build();
+    let thursday = Event::builder().day(Day::Thursday).room("101").build();
+    // Both occurrences change their rooms, the Thursday one is scraped first
+    let moved_thursday = Event::builder().day(Day::Thursday).room("202").build();
+    let moved_monday = Event::builder().room("303").build();
+
+    let diff = TimetableDiff::between(
+        stored(&[monday, thursday]),
+        &weeks(&[(week(), &[moved_thursday.clone(), moved_monday.clone()])]),
+    );
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+    let mut pairs = diff
+        .moved
+        .iter()
+        .map(|(stored, event)| (stored.id, event.clone()))
+        .collect::<Vec<_>>();
+    pairs.sort_by_key(|(id, _)| *id);
+    assert_eq!(pairs, [(1, moved_monday), (2, moved_thursday)]);
+}
+
+#[test]
+fn same_class_twice_a_day_is_paired_by_time() {
+    let morning = Event::builder().starts_at(9).room("101").build();
+    let afternoon = Event::builder().starts_at(13).room("101").build();
+    let moved_afternoon = Event::builder().starts_at(13).room("202").build();
+    let moved_morning = Event {
+        end_time: NaiveTime::from_hms_opt(10, 30, 0).unwrap(),
+        ..Event::builder().starts_at(9).room("303").build()
+    };
+
+    let diff = TimetableDiff::between(
+        stored(&[morning, afternoon]),
+        &weeks(&[(week(), &[moved_afternoon.clone(), moved_morning.clone()])]),
+    );
+    let mut pairs = diff
+        .moved
+        .iter()
+        .map(|(stored, event)| (stored.id, event.clone()))
+        .collect::<Vec<_>>();
+    pairs.sort_by_key(|(id, _)| *id);
+    assert_eq!(pairs, [(1, moved_morning), (2, moved_afternoon)]);
+}
@@ -1,18 +1,28 @@
-use crate::database::schema::*;
-use chrono::NaiveTime;
-use diesel::{prelude::*, sql_types::Text, AsExpression, FromSqlRow};
+use std::collections::HashMap;
+
+use crate::database::{connection::MultiBackend, schema::*};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql},
+    prelude::*,
+    serialize::{self, Output, ToSql},
+    sql_types::{Integer, Text},
+    AsExpression, FromSqlRow,
+};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 pub type Uuid = String;
 
-#[derive(Queryable, Insertable, Debug, Serialize, Deserialize)]
+#[derive(Queryable, Insertable, Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = faculties)]
 pub struct Faculty {
     pub uuid: Uuid,
     pub name: String,
 }
 
-#[derive(Queryable, Insertable, Clone, Debug, Serialize, Deserialize)]
+#[derive(Queryable, Insertable, Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[diesel(table_name = groups)]
 pub struct Group {
     pub uuid: Uuid,
@@ -21,9 +31,21 @@ pub struct Group {
 }
 
 #[derive(
-    Eq, PartialEq, Hash, Clone, Copy, Debug, Serialize, Deserialize, AsExpression, FromSqlRow,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    ToSchema,
 )]
-#[diesel(sql_type = Text)]
+#[diesel(sql_type = Integer)]
 pub enum Day {
     Monday,
     Tuesday,
@@ -52,49 +74,592 @@ impl Day {
             }
         }
     }
+
+    /// Returns the day of the week of the date, classes do not take place on Sundays
+    pub fn of(date: NaiveDate) -> Option<Self> {
+        match date.weekday() {
+            Weekday::Mon => Some(Self::Monday),
+            Weekday::Tue => Some(Self::Tuesday),
+            Weekday::Wed => Some(Self::Wednesday),
+            Weekday::Thu => Some(Self::Thursday),
+            Weekday::Fri => Some(Self::Friday),
+            Weekday::Sat => Some(Self::Saturday),
+            Weekday::Sun => None,
+        }
+    }
+}
+
+/// Days are stored as ISO weekday numbers, 1 is Monday
+impl<DB: Backend> ToSql<Integer, DB> for Day
+where
+    i32: ToSql<Integer, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        let number: &'static i32 = match self {
+            Self::Monday => &1,
+            Self::Tuesday => &2,
+            Self::Wednesday => &3,
+            Self::Thursday => &4,
+            Self::Friday => &5,
+            Self::Saturday => &6,
+        };
+        number.to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Integer, DB> for Day
+where
+    i32: FromSql<Integer, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        match i32::from_sql(bytes)? {
+            1 => Ok(Self::Monday),
+            2 => Ok(Self::Tuesday),
+            3 => Ok(Self::Wednesday),
+            4 => Ok(Self::Thursday),
+            5 => Ok(Self::Friday),
+            6 => Ok(Self::Saturday),
+            number => Err(format!("Invalid day of the week: {number}").into()),
+        }
+    }
+}
+
+/// Kind of a class as written in the timetable
+#[derive(
+    Eq,
+    PartialEq,
+    Hash,
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    AsExpression,
+    FromSqlRow,
+    ToSchema,
+)]
+#[diesel(sql_type = Text)]
+pub enum EventKind {
+    Lecture,
+    Seminar,
+    Lab,
+}
+
+impl EventKind {
+    /// Recognises the class kind in a russian timetable caption, e.g. "Лабораторная работа"
+    pub fn from_russian(caption: &str) -> Option<Self> {
+        let caption = caption.to_lowercase();
+        if caption.starts_with("лекц") {
+            Some(Self::Lecture)
+        } else if caption.starts_with("лаб") {
+            Some(Self::Lab)
+        } else if caption.starts_with("семинар") || caption.starts_with("практ") {
+            Some(Self::Seminar)
+        } else {
+            None
+        }
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Kinds are stored as their names, e.g. `Lecture`
+impl<DB: Backend> ToSql<Text, DB> for EventKind
+where
+    str: ToSql<Text, DB>,
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, DB>) -> serialize::Result {
+        let name = match self {
+            Self::Lecture => "Lecture",
+            Self::Seminar => "Seminar",
+            Self::Lab => "Lab",
+        };
+        name.to_sql(out)
+    }
+}
+
+impl<DB: Backend> FromSql<Text, DB> for EventKind
+where
+    String: FromSql<Text, DB>,
+{
+    fn from_sql(bytes: DB::RawValue<'_>) -> deserialize::Result<Self> {
+        match String::from_sql(bytes)?.as_str() {
+            "Lecture" => Ok(Self::Lecture),
+            "Seminar" => Ok(Self::Seminar),
+            "Lab" => Ok(Self::Lab),
+            name => Err(format!("Invalid class kind: {name}").into()),
+        }
+    }
+}
+
+/// Returns the Monday of the week containing `date`
+pub fn week_start_of(date: NaiveDate) -> NaiveDate {
+    date - chrono::Duration::days(date.weekday().num_days_from_monday().into())
+}
+
+/// Returns the Monday of the ISO week `week` closest to `today`.
+/// Week numbers are ambiguous across New Year, so the neighbouring years are considered too
+pub fn week_start_for(week: u32, today: NaiveDate) -> Option<NaiveDate> {
+    [today.year() - 1, today.year(), today.year() + 1]
+        .into_iter()
+        .filter_map(|year| NaiveDate::from_isoywd_opt(year, week, Weekday::Mon))
+        .min_by_key(|date| (*date - today).num_days().abs())
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Event {
     pub name: String,
     pub day: Day,
     pub start_time: NaiveTime,
     pub end_time: NaiveTime,
     pub student_group: Uuid,
+    /// Monday of the week this event takes place in
+    pub week_start: NaiveDate,
+    /// Auditorium the class takes place in
+    pub room: Option<String>,
+    /// Lecturer's name as written in the timetable, e.g. "Иванов И.И."
+    pub teacher: Option<String>,
+    pub kind: Option<EventKind>,
+    /// Subgroup of the student group attending the class, the whole group if absent
+    pub subgroup: Option<String>,
+    /// Link to an online class
+    pub link: Option<String>,
 }
 
-#[derive(Insertable, Clone, Debug, Serialize, Deserialize)]
-#[diesel(table_name = timetables)]
+impl Event {
+    /// Date the event takes place on
+    pub fn date(&self) -> NaiveDate {
+        let offset = match self.day {
+            Day::Monday => 0,
+            Day::Tuesday => 1,
+            Day::Wednesday => 2,
+            Day::Thursday => 3,
+            Day::Friday => 4,
+            Day::Saturday => 5,
+        };
+        self.week_start + chrono::Duration::days(offset)
+    }
+}
+
+#[derive(Insertable, AsChangeset, Clone, Debug, Serialize, Deserialize)]
+#[diesel(table_name = timetables, treat_none_as_null = true)]
 pub struct InsertableEvent {
     pub name: String,
-    pub day: String,
-    pub start_time: String,
-    pub end_time: String,
+    pub day: Day,
+    pub start_time: NaiveTime,
+    pub end_time: NaiveTime,
     pub student_group: Uuid,
+    pub week_start: NaiveDate,
+    pub room: Option<String>,
+    pub teacher: Option<String>,
+    pub kind: Option<EventKind>,
+    pub subgroup: Option<String>,
+    pub link: Option<String>,
 }
 
 impl From<Event> for InsertableEvent {
     fn from(value: Event) -> Self {
         Self {
             name: value.name,
-            day: serde_json::to_string(&value.day).unwrap(),
-            start_time: value.start_time.format("%H:%M").to_string(),
-            end_time: value.end_time.format("%H:%M").to_string(),
+            day: value.day,
+            start_time: value.start_time,
+            end_time: value.end_time,
             student_group: value.student_group,
+            week_start: value.week_start,
+            room: value.room,
+            teacher: value.teacher,
+            kind: value.kind,
+            subgroup: value.subgroup,
+            link: value.link,
         }
     }
 }
 
-impl Queryable<timetables::SqlType, diesel::sqlite::Sqlite> for Event {
-    type Row = (i32, String, String, String, String, String);
+/// Timetable of a group as shown on its webpage. Every week tab of the page is present,
+/// including those without classes, so classes of a cleared week are known to be cancelled
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ScrapedTimetable {
+    pub group: Uuid,
+    /// Events of every week by its Monday, in the order they are listed
+    pub weeks: HashMap<NaiveDate, Vec<Event>>,
+}
+
+impl ScrapedTimetable {
+    pub fn new(group: &Uuid) -> Self {
+        Self {
+            group: group.clone(),
+            weeks: HashMap::new(),
+        }
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &Event> {
+        self.weeks.values().flatten()
+    }
+
+    pub fn into_events(self) -> Vec<Event> {
+        self.weeks.into_values().flatten().collect()
+    }
+
+    /// Events of the week by day, days without classes are omitted
+    pub fn week_by_day(&self, week_start: NaiveDate) -> HashMap<Day, Vec<Event>> {
+        let mut timetable: HashMap<Day, Vec<Event>> = HashMap::new();
+        for event in self.weeks.get(&week_start).into_iter().flatten() {
+            timetable.entry(event.day).or_default().push(event.clone());
+        }
+        timetable
+    }
+}
+
+/// Event together with the id of its row in the `timetables` table
+#[derive(Clone, Debug)]
+pub struct StoredEvent {
+    pub id: i32,
+    pub event: Event,
+}
+
+impl Queryable<timetables::SqlType, MultiBackend> for Event {
+    type Row = <StoredEvent as Queryable<timetables::SqlType, MultiBackend>>::Row;
 
     fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
-        Ok(Self {
+        StoredEvent::build(row).map(|stored| stored.event)
+    }
+}
+
+impl Queryable<timetables::SqlType, MultiBackend> for StoredEvent {
+    type Row = (
+        i32,
+        String,
+        Day,
+        NaiveTime,
+        NaiveTime,
+        String,
+        NaiveDate,
+        Option<String>,
+        Option<String>,
+        Option<EventKind>,
+        Option<String>,
+        Option<String>,
+        Option<i32>,
+    );
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        let event = Event {
             name: row.1,
-            day: serde_json::from_str(&row.2).unwrap(),
-            start_time: NaiveTime::parse_from_str(&row.3, "%H:%M").unwrap(),
-            end_time: NaiveTime::parse_from_str(&row.4, "%H:%M").unwrap(),
+            day: row.2,
+            start_time: row.3,
+            end_time: row.4,
             student_group: row.5,
+            week_start: row.6,
+            room: row.7,
+            teacher: row.8,
+            kind: row.9,
+            subgroup: row.10,
+            link: row.11,
+        };
+        Ok(Self { id: row.0, event })
+    }
+}
+
+/// Lecturer found in the parsed timetables
+#[derive(Queryable, Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = instructors)]
+pub struct Instructor {
+    pub id: i32,
+    /// Name as written in the timetable, e.g. "Иванов И.И."
+    pub name: String,
+}
+
+/// Auditorium found in the parsed timetables
+#[derive(Queryable, Insertable, Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = rooms)]
+pub struct Room {
+    /// Name as written in the timetable, e.g. "101" or "ФМ 311"
+    pub name: String,
+    /// Building the room is in, if its name starts with one
+    pub building: Option<String>,
+}
+
+impl Room {
+    /// Names of rooms outside the main building start with the building
+    /// separated by a space, e.g. "ФМ 311"
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            building: name
+                .split_once(' ')
+                .map(|(building, _)| building.to_string()),
+        }
+    }
+}
+
+/// Class which may be attended by several student groups at once,
+/// e.g. a lecture in an instructor's or a room's timetable
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SharedClass {
+    #[serde(flatten)]
+    pub event: Event,
+    /// Every group attending the class, `event.student_group` is the first of them
+    pub student_groups: Vec<Uuid>,
+}
+
+impl SharedClass {
+    /// Merges events which differ only in the student group, e.g. a lecture for the whole course
+    pub fn merge(mut events: Vec<Event>) -> Vec<Self> {
+        events.sort_by(|a, b| a.student_group.cmp(&b.student_group));
+        let mut classes: Vec<Self> = vec![];
+        for event in events {
+            let same_class = classes.iter_mut().find(|class| {
+                let class = &class.event;
+                class.week_start == event.week_start
+                    && class.day == event.day
+                    && class.start_time == event.start_time
+                    && class.end_time == event.end_time
+                    && class.name == event.name
+                    && class.room == event.room
+                    && class.kind == event.kind
+            });
+            match same_class {
+                Some(class) => class.student_groups.push(event.student_group),
+                None => classes.push(Self {
+                    student_groups: vec![event.student_group.clone()],
+                    event,
+                }),
+            }
+        }
+        classes.sort_by_key(|class| {
+            (
+                class.event.week_start,
+                class.event.day,
+                class.event.start_time,
+            )
+        });
+        classes
+    }
+}
+
+/// Stored data whose last modification is tracked, so clients can make conditional requests
+#[derive(Clone, Copy, Debug)]
+pub enum Resource<'a> {
+    /// List of every faculty
+    Faculties,
+    /// Groups of the faculty with this UUID
+    Groups(&'a str),
+    /// Every week of the timetable of the group with this UUID
+    Timetable(&'a str),
+}
+
+impl Resource<'_> {
+    /// Key of the resource in the `last_modified` table
+    pub fn key(&self) -> String {
+        match self {
+            Self::Faculties => String::from("faculties"),
+            Self::Groups(faculty) => format!("groups:{faculty}"),
+            Self::Timetable(group) => format!("timetable:{group}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    /// The class was moved to another time or room, or its details changed
+    Moved,
+}
+
+#[derive(Insertable, Clone, Debug)]
+#[diesel(table_name = timetable_changes)]
+pub struct InsertableChange {
+    pub student_group: Uuid,
+    pub kind: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub changed_at: String,
+}
+
+impl InsertableChange {
+    pub fn new(
+        kind: ChangeKind,
+        before: Option<&Event>,
+        after: Option<&Event>,
+        changed_at: NaiveDateTime,
+    ) -> Self {
+        let student_group = before.or(after).map(|event| event.student_group.clone());
+        Self {
+            student_group: student_group.unwrap_or_default(),
+            kind: serde_json::to_string(&kind).unwrap(),
+            before: before.map(|event| serde_json::to_string(event).unwrap()),
+            after: after.map(|event| serde_json::to_string(event).unwrap()),
+            changed_at: changed_at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        }
+    }
+}
+
+/// Single recorded modification of a group's timetable
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct TimetableChange {
+    pub id: i32,
+    pub student_group: Uuid,
+    pub kind: ChangeKind,
+    /// Event before the change, absent for added events
+    pub before: Option<Event>,
+    /// Event after the change, absent for removed events
+    pub after: Option<Event>,
+    pub changed_at: NaiveDateTime,
+}
+
+impl Queryable<timetable_changes::SqlType, MultiBackend> for TimetableChange {
+    type Row = (i32, String, String, Option<String>, Option<String>, String);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(Self {
+            id: row.0,
+            student_group: row.1,
+            kind: serde_json::from_str(&row.2)?,
+            before: row
+                .3
+                .map(|event| serde_json::from_str(&event))
+                .transpose()?,
+            after: row
+                .4
+                .map(|event| serde_json::from_str(&event))
+                .transpose()?,
+            changed_at: NaiveDateTime::parse_from_str(&row.5, "%Y-%m-%dT%H:%M:%S")?,
         })
     }
 }
+
+/// Subscription of an URL to timetable changes
+#[derive(Queryable, Clone, Debug, Serialize, ToSchema)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    /// Key of the HMAC signature of delivered payloads, never returned to clients
+    #[serde(skip)]
+    pub secret: String,
+    /// Changes of every group are delivered when absent
+    pub student_group: Option<Uuid>,
+    pub created_at: String,
+}
+
+#[derive(Insertable, Clone, Debug, Deserialize, ToSchema)]
+#[diesel(table_name = webhooks)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub student_group: Option<Uuid>,
+    #[serde(skip, default = "current_timestamp")]
+    pub created_at: String,
+}
+
+fn current_timestamp() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    /// Delivery failed too many times and will not be retried
+    Dead,
+}
+
+/// Delivery of a single timetable change to a webhook
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook: i32,
+    pub change: i32,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+}
+
+impl Queryable<webhook_deliveries::SqlType, MultiBackend> for WebhookDelivery {
+    type Row = (i32, i32, i32, String, i32, String, Option<String>);
+
+    fn build(row: Self::Row) -> diesel::deserialize::Result<Self> {
+        Ok(Self {
+            id: row.0,
+            webhook: row.1,
+            change: row.2,
+            status: serde_json::from_str(&row.3)?,
+            attempts: row.4,
+            next_attempt_at: NaiveDateTime::parse_from_str(&row.5, "%Y-%m-%dT%H:%M:%S")?,
+            last_error: row.6,
+        })
+    }
+}
+
+/// Migrations of the database schema
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SchemaVersion {
+    /// Version of the latest applied migration, absent if none is applied
+    pub version: Option<String>,
+    /// Versions of the embedded migrations which are not applied yet
+    pub pending: Vec<String>,
+}
+
+/// Amount of the stored data
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DatabaseStats {
+    pub faculties: i64,
+    pub groups: i64,
+    pub events: i64,
+    pub instructors: i64,
+    pub rooms: i64,
+    pub changes: i64,
+    pub webhooks: i64,
+    pub pending_deliveries: i64,
+    pub dead_deliveries: i64,
+    /// Mondays of the earliest and the latest stored weeks
+    pub weeks: Option<(NaiveDate, NaiveDate)>,
+    /// Earliest of the last scrapes of every resource, the stalest data was scraped then
+    pub oldest_scrape: Option<NaiveDateTime>,
+}
+
+/// Number of rows deleted by pruning
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct PruneStats {
+    pub events: usize,
+    pub changes: usize,
+    pub deliveries: usize,
+}
+
+/// Version of the export bundle format, increased on incompatible changes
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// Where and when the bundle was exported
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct BundleMetadata {
+    pub exported_at: NaiveDateTime,
+    /// Version of the backend which exported the bundle
+    pub backend_version: String,
+    /// Latest migration applied to the exported database
+    pub schema_version: Option<String>,
+}
+
+/// Every scraped entity stored in the database
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Bundle {
+    pub format_version: u32,
+    pub metadata: BundleMetadata,
+    pub faculties: Vec<Faculty>,
+    pub groups: Vec<Group>,
+    /// Events of every group for every stored week
+    pub timetables: Vec<Event>,
+}
+
+/// Outcome of importing a bundle
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub faculties: usize,
+    pub groups: usize,
+    pub events: usize,
+    /// Entities differing from the stored ones, the stored ones are kept
+    pub conflicts: Vec<String>,
+    /// Entities referring to faculties or groups which are neither imported nor stored,
+    /// nothing is imported when there are any
+    pub invalid: Vec<String>,
+}
@@ -15,22 +15,95 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    instructors (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+diesel::table! {
+    last_modified (resource) {
+        resource -> Text,
+        modified_at -> Text,
+        scraped_at -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    rooms (name) {
+        name -> Text,
+        building -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     timetables (id) {
         id -> Integer,
         name -> Text,
-        day -> Text,
-        start_time -> Text,
-        end_time -> Text,
+        day -> Integer,
+        start_time -> Time,
+        end_time -> Time,
         student_group -> Text,
+        week_start -> Date,
+        room -> Nullable<Text>,
+        teacher -> Nullable<Text>,
+        kind -> Nullable<Text>,
+        subgroup -> Nullable<Text>,
+        link -> Nullable<Text>,
+        instructor -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    timetable_changes (id) {
+        id -> Integer,
+        student_group -> Text,
+        kind -> Text,
+        before -> Nullable<Text>,
+        after -> Nullable<Text>,
+        changed_at -> Text,
+    }
+}
+
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Integer,
+        webhook -> Integer,
+        change -> Integer,
+        status -> Text,
+        attempts -> Integer,
+        next_attempt_at -> Text,
+        last_error -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Integer,
+        url -> Text,
+        secret -> Text,
+        student_group -> Nullable<Text>,
+        created_at -> Text,
     }
 }
 
 diesel::joinable!(groups -> faculties (faculty));
+diesel::joinable!(timetable_changes -> groups (student_group));
 diesel::joinable!(timetables -> groups (student_group));
+diesel::joinable!(timetables -> instructors (instructor));
+diesel::joinable!(webhook_deliveries -> timetable_changes (change));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook));
+diesel::joinable!(webhooks -> groups (student_group));
 
 diesel::allow_tables_to_appear_in_same_query!(
     faculties,
     groups,
+    instructors,
+    last_modified,
+    rooms,
+    timetable_changes,
     timetables,
+    webhook_deliveries,
+    webhooks,
 );
@@ -0,0 +1,609 @@
+//! Tests of the `Database` API, run against every enabled backend.
+//! SQLite databases are temporary files, PostgreSQL ones are created on the server
+//! at `POSTGRES_TEST_URL` and dropped afterwards. PostgreSQL tests are ignored by default,
+//! run them with `cargo test --features postgres -- --include-ignored`
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::Duration;
+
+use super::*;
+
+/// Fresh migrated database removed when dropped
+struct TestDatabase {
+    db: Option<Database>,
+    #[cfg(feature = "postgres")]
+    postgres: Option<(String, String)>,
+    sqlite: Option<std::path::PathBuf>,
+}
+
+/// Unique name of a test database, tests run in parallel
+fn unique_name() -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    format!(
+        "backend_test_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+fn migrated(db: Database) -> Database {
+    db.run_migrations().unwrap();
+    db
+}
+
+impl TestDatabase {
+    fn sqlite() -> Self {
+        let path = env::temp_dir().join(format!("{}.db", unique_name()));
+        Self {
+            db: Some(migrated(Database::connect(path.to_str().unwrap()).unwrap())),
+            #[cfg(feature = "postgres")]
+            postgres: None,
+            sqlite: Some(path),
+        }
+    }
+
+    /// Database on the server at `POSTGRES_TEST_URL`, e.g. `postgres://postgres@localhost/postgres`
+    #[cfg(feature = "postgres")]
+    fn postgres() -> Self {
+        let server_url =
+            env::var("POSTGRES_TEST_URL").expect("POSTGRES_TEST_URL must be set for this test");
+        let name = unique_name();
+        diesel::sql_query(format!("CREATE DATABASE {name}"))
+            .execute(&mut diesel::PgConnection::establish(&server_url).unwrap())
+            .unwrap();
+
+        let (server, _) = server_url.rsplit_once('/').unwrap();
+        let db = Database::connect(&format!("{server}/{name}")).unwrap();
+        Self {
+            db: Some(migrated(db)),
+            postgres: Some((server_url, name)),
+            sqlite: None,
+        }
+    }
+}
+
+impl Deref for TestDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().unwrap()
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        // Connections of the pool must be closed first
+        self.db.take();
+        #[cfg(feature = "postgres")]
+        if let Some((server_url, name)) = &self.postgres {
+            if let Ok(mut conn) = diesel::PgConnection::establish(server_url) {
+                let _ = diesel::sql_query(format!("DROP DATABASE {name} WITH (FORCE)"))
+                    .execute(&mut conn);
+            }
+        }
+        if let Some(path) = &self.sqlite {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+            }
+        }
+    }
+}
+
+/// Generates a test per backend for every test function taking the database
+macro_rules! backend_tests {
+    ($($test:ident),* $(,)?) => {
+        mod sqlite {
+            $(
+                #[test]
+                fn $test() {
+                    super::$test(&super::TestDatabase::sqlite());
+                }
+            )*
+        }
+
+        #[cfg(feature = "postgres")]
+        mod postgres {
+            $(
+                #[test]
+                #[ignore = "needs POSTGRES_TEST_URL"]
+                fn $test() {
+                    super::$test(&super::TestDatabase::postgres());
+                }
+            )*
+        }
+    };
+}
+
+backend_tests!(
+    migrations_are_applied_once,
+    faculties_and_groups_are_stored_once,
+    renamed_faculties_and_groups_are_updated,
+    timetable_changes_are_applied_and_recorded,
+    cleared_weeks_are_emptied_and_scrapes_recorded,
+    instructors_and_rooms_are_linked,
+    changes_are_delivered_to_subscribed_webhooks,
+    stats_count_and_prune_deletes_old_data,
+    text_timetables_are_converted_to_typed_columns,
+    exported_bundle_is_imported_once,
+    import_reports_conflicts_and_keeps_stored_data,
+    import_with_unknown_references_stores_nothing,
+);
+
+const FACULTY: &str = "faculty";
+const GROUP: &str = "group";
+
+fn week() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2023, 6, 12).unwrap()
+}
+
+fn event(name: &str, day: Day, start: u32, room: &str, teacher: &str) -> Event {
+    Event::builder()
+        .name(name)
+        .group(GROUP)
+        .week(week())
+        .day(day)
+        .starts_at(start)
+        .room(room)
+        .teacher(teacher)
+        .build()
+}
+
+/// Scraped timetable of the group with the weeks of the events
+fn timetable(events: &[Event]) -> ScrapedTimetable {
+    let mut timetable = ScrapedTimetable::new(&GROUP.to_string());
+    for event in events {
+        timetable
+            .weeks
+            .entry(event.week_start)
+            .or_default()
+            .push(event.clone());
+    }
+    timetable
+}
+
+fn store_group(db: &Database) {
+    db.update_faculties(&[Faculty {
+        uuid: FACULTY.to_string(),
+        name: String::from("Faculty"),
+    }])
+    .unwrap();
+    db.update_groups(&[Group {
+        uuid: GROUP.to_string(),
+        name: String::from("Group"),
+        faculty: FACULTY.to_string(),
+    }])
+    .unwrap();
+}
+
+fn migrations_are_applied_once(db: &Database) {
+    let schema = db.get_schema_version().unwrap();
+    assert!(schema.version.is_some());
+    assert!(schema.pending.is_empty());
+    assert!(db.run_migrations().unwrap().is_empty());
+}
+
+fn faculties_and_groups_are_stored_once(db: &Database) {
+    store_group(db);
+    store_group(db);
+
+    assert_eq!(db.get_faculties().unwrap().len(), 1);
+    assert_eq!(db.get_groups().unwrap().len(), 1);
+    assert!(db.get_group(&GROUP.to_string()).unwrap().is_some());
+    assert!(db.get_group(&String::from("missing")).unwrap().is_none());
+    let by_faculty = db.get_groups_for_faculty(&FACULTY.to_string()).unwrap();
+    assert_eq!(by_faculty[FACULTY].len(), 1);
+    let (_, faculty) = &db.get_groups_with_faculties().unwrap()[0];
+    assert_eq!(faculty.as_ref().unwrap().uuid, FACULTY);
+
+    let resource = Resource::Groups(FACULTY);
+    assert!(db.get_last_modified(resource).unwrap().is_some());
+    assert!(db.get_scraped_at(resource).unwrap().is_some());
+    assert!(db
+        .get_scraped_at(Resource::Timetable(GROUP))
+        .unwrap()
+        .is_none());
+}
+
+fn renamed_faculties_and_groups_are_updated(db: &Database) {
+    store_group(db);
+    // Modification times are stored to the second, so they are moved back
+    // for the later modifications to be told apart from the first one
+    let long_ago = week().and_hms_opt(0, 0, 0).unwrap();
+    for resource in [Resource::Faculties, Resource::Groups(FACULTY)] {
+        touch(&mut db.conn().unwrap(), resource, long_ago).unwrap();
+    }
+    let modified = |resource| db.get_last_modified(resource).unwrap();
+    let faculties_modified = modified(Resource::Faculties);
+    let groups_modified = modified(Resource::Groups(FACULTY));
+    assert_eq!(faculties_modified, Some(long_ago));
+
+    // Unchanged names are not counted as modifications
+    store_group(db);
+    assert_eq!(modified(Resource::Faculties), faculties_modified);
+    assert_eq!(modified(Resource::Groups(FACULTY)), groups_modified);
+
+    db.update_faculties(&[Faculty {
+        uuid: FACULTY.to_string(),
+        name: String::from("Renamed faculty"),
+    }])
+    .unwrap();
+    db.update_groups(&[Group {
+        uuid: GROUP.to_string(),
+        name: String::from("Renamed group"),
+        faculty: FACULTY.to_string(),
+    }])
+    .unwrap();
+    assert_eq!(db.get_faculties().unwrap()[0].name, "Renamed faculty");
+    let group = db.get_group(&GROUP.to_string()).unwrap().unwrap();
+    assert_eq!(group.name, "Renamed group");
+    assert_ne!(modified(Resource::Faculties), faculties_modified);
+    assert_ne!(modified(Resource::Groups(FACULTY)), groups_modified);
+}
+
+fn timetable_changes_are_applied_and_recorded(db: &Database) {
+    store_group(db);
+    let math = event("Math", Day::Monday, 9, "101", "Иванов И.И.");
+    let physics = event("Physics", Day::Tuesday, 11, "102", "Петров П.П.");
+
+    let diff = db
+        .update_timetable(&timetable(&[math.clone(), physics.clone()]))
+        .unwrap();
+    assert_eq!(diff.added.len(), 2);
+    assert!(db
+        .update_timetable(&timetable(&[math.clone(), physics.clone()]))
+        .unwrap()
+        .is_empty());
+
+    let moved_math = Event {
+        start_time: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+        ..math.clone()
+    };
+    let diff = db
+        .update_timetable(&timetable(std::slice::from_ref(&moved_math)))
+        .unwrap();
+    assert_eq!((diff.added.len(), diff.removed.len()), (0, 1));
+    assert_eq!(diff.moved.len(), 1);
+
+    let stored = db
+        .get_timetable_for_group(&GROUP.to_string(), &week())
+        .unwrap();
+    assert_eq!(stored[&Day::Monday], vec![moved_math.clone()]);
+    assert!(!stored.contains_key(&Day::Tuesday));
+    assert_eq!(db.export().unwrap().timetables, vec![moved_math.clone()]);
+
+    let changes = db.get_changes_for_group(&GROUP.to_string(), None).unwrap();
+    assert_eq!(changes.len(), 4);
+    assert_eq!(changes[3].before, Some(math));
+    assert_eq!(changes[3].after, Some(moved_math));
+    let since = changes[3].changed_at;
+    assert!(db
+        .get_changes_for_group(&GROUP.to_string(), Some(since))
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_last_modified(Resource::Timetable(GROUP))
+        .unwrap()
+        .is_some());
+}
+
+fn cleared_weeks_are_emptied_and_scrapes_recorded(db: &Database) {
+    store_group(db);
+    let math = event("Math", Day::Monday, 9, "101", "A");
+    let earlier = Event {
+        week_start: week() - Duration::weeks(1),
+        ..math.clone()
+    };
+    db.update_timetable(&timetable(&[earlier.clone(), math]))
+        .unwrap();
+
+    // The week is still shown on the webpage but has no classes anymore
+    let mut cleared = timetable(&[]);
+    cleared.weeks.insert(week(), vec![]);
+    let diff = db.update_timetable(&cleared).unwrap();
+    assert_eq!((diff.added.len(), diff.removed.len()), (0, 1));
+    assert!(db
+        .get_timetable_for_group(&GROUP.to_string(), &week())
+        .unwrap()
+        .is_empty());
+    assert_eq!(db.export().unwrap().timetables, vec![earlier]);
+
+    let other_group = String::from("other");
+    db.update_groups(&[Group {
+        uuid: other_group.clone(),
+        name: String::from("Other"),
+        faculty: FACULTY.to_string(),
+    }])
+    .unwrap();
+    assert!(db
+        .update_timetable(&ScrapedTimetable::new(&other_group))
+        .unwrap()
+        .is_empty());
+    assert!(db
+        .get_scraped_at(Resource::Timetable(&other_group))
+        .unwrap()
+        .is_some());
+}
+
+fn instructors_and_rooms_are_linked(db: &Database) {
+    store_group(db);
+    let math = event("Math", Day::Monday, 9, "ФМ 311", "Иванов И.И.");
+    let lab = event("Lab", Day::Monday, 11, "101", "Иванов И.И.");
+    db.update_timetable(&timetable(&[math.clone(), lab.clone()]))
+        .unwrap();
+
+    let instructors = db.get_instructors().unwrap();
+    assert_eq!(instructors.len(), 1);
+    assert_eq!(instructors[0].name, "Иванов И.И.");
+    let mut events = db
+        .get_events_for_instructor(instructors[0].id, Some(&week()))
+        .unwrap();
+    events.sort_by_key(|event| event.start_time);
+    assert_eq!(events, vec![math.clone(), lab]);
+
+    let room = db.get_room("ФМ 311").unwrap().unwrap();
+    assert_eq!(room.building.as_deref(), Some("ФМ"));
+    assert_eq!(
+        db.get_events_in_room("ФМ 311", &week()).unwrap(),
+        vec![math]
+    );
+
+    let free = db
+        .get_free_rooms(
+            &week(),
+            Day::Monday,
+            NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(
+        free.iter()
+            .map(|room| room.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["101"]
+    );
+}
+
+fn changes_are_delivered_to_subscribed_webhooks(db: &Database) {
+    store_group(db);
+    let webhook = |group: Option<&str>| NewWebhook {
+        url: String::from("http://localhost/hook"),
+        secret: String::from("secret"),
+        student_group: group.map(ToString::to_string),
+        created_at: String::from("2023-06-12T00:00:00"),
+    };
+    let for_group = db.add_webhook(&webhook(Some(GROUP))).unwrap();
+    let for_all = db.add_webhook(&webhook(None)).unwrap();
+    assert_ne!(for_group.id, for_all.id);
+
+    let math = event("Math", Day::Monday, 9, "101", "A");
+    db.update_timetable(&timetable(std::slice::from_ref(&math)))
+        .unwrap();
+    let now = chrono::Utc::now().naive_utc() + Duration::minutes(1);
+    // Classes of the first scrape are not delivered
+    assert!(db.get_due_deliveries(now, 10).unwrap().is_empty());
+
+    // Repeated scrapes within the same second enqueue every change once
+    let physics = event("Physics", Day::Tuesday, 9, "102", "B");
+    db.update_timetable(&timetable(&[math.clone(), physics.clone()]))
+        .unwrap();
+    let lab = event("Lab", Day::Friday, 9, "103", "B");
+    db.update_timetable(&timetable(&[math, physics, lab]))
+        .unwrap();
+    let due = db.get_due_deliveries(now, 10).unwrap();
+    assert_eq!(due.len(), 4);
+    let mut delivered = due
+        .iter()
+        .filter_map(|(_, _, change)| change.after.as_ref().map(|event| event.name.as_str()))
+        .collect::<Vec<_>>();
+    delivered.sort_unstable();
+    assert_eq!(delivered, ["Lab", "Lab", "Physics", "Physics"]);
+
+    let (delivery, _, change) = &due[0];
+    assert_eq!(change.kind, ChangeKind::Added);
+    db.update_delivery(
+        delivery.id,
+        DeliveryStatus::Dead,
+        now,
+        Some(String::from("500")),
+    )
+    .unwrap();
+    let dead = db.get_dead_deliveries().unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!(
+        (dead[0].attempts, dead[0].last_error.as_deref()),
+        (1, Some("500"))
+    );
+    assert_eq!(db.get_due_deliveries(now, 10).unwrap().len(), 3);
+
+    assert!(db.delete_webhook(for_all.id).unwrap());
+    assert!(!db.delete_webhook(for_all.id).unwrap());
+}
+
+fn stats_count_and_prune_deletes_old_data(db: &Database) {
+    store_group(db);
+    let old = Event {
+        week_start: week() - Duration::weeks(1),
+        ..event("Math", Day::Monday, 9, "101", "A")
+    };
+    let current = event("Math", Day::Monday, 9, "101", "A");
+    db.update_timetable(&timetable(&[old.clone(), current.clone()]))
+        .unwrap();
+
+    let stats = db.get_stats().unwrap();
+    assert_eq!((stats.faculties, stats.groups, stats.events), (1, 1, 2));
+    assert_eq!((stats.instructors, stats.rooms, stats.changes), (1, 1, 2));
+    assert_eq!(stats.weeks, Some((old.week_start, current.week_start)));
+    assert!(stats.oldest_scrape.is_some());
+
+    // Changes are recorded now, so only the old events are pruned
+    let pruned = db.prune(&week()).unwrap();
+    assert_eq!(
+        (pruned.events, pruned.changes, pruned.deliveries),
+        (1, 0, 0)
+    );
+    assert_eq!(db.export().unwrap().timetables, vec![current]);
+}
+
+fn text_timetables_are_converted_to_typed_columns(db: &Database) {
+    let conn = &mut db.conn().unwrap();
+    // Migrations applied after the conversion are reverted too
+    while conn
+        .revert_last_migration(conn.migrations())
+        .unwrap()
+        .to_string()
+        != "20230701100000"
+    {}
+    for statement in [
+        "INSERT INTO faculties (uuid, name) VALUES ('faculty', 'Faculty')",
+        "INSERT INTO groups (uuid, name, faculty) VALUES ('group', 'Group', 'faculty')",
+        "INSERT INTO timetables (name, day, start_time, end_time, student_group, week_start, kind) \
+         VALUES ('Math', '\"Saturday\"', '15:30', '16:50', 'group', '2023-06-12', '\"Lab\"')",
+    ] {
+        diesel::sql_query(statement).execute(conn).unwrap();
+    }
+
+    db.run_migrations().unwrap();
+    let events = db.export().unwrap().timetables;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].day, Day::Saturday);
+    assert_eq!(events[0].kind, Some(EventKind::Lab));
+    assert_eq!(
+        (
+            events[0].start_time,
+            events[0].end_time,
+            events[0].week_start
+        ),
+        (
+            NaiveTime::from_hms_opt(15, 30, 0).unwrap(),
+            NaiveTime::from_hms_opt(16, 50, 0).unwrap(),
+            week()
+        )
+    );
+}
+
+fn bundle(groups: &[Group], timetables: &[Event]) -> Bundle {
+    Bundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        metadata: BundleMetadata {
+            exported_at: week().and_hms_opt(12, 0, 0).unwrap(),
+            backend_version: String::from("0.1.0"),
+            schema_version: None,
+        },
+        faculties: vec![Faculty {
+            uuid: FACULTY.to_string(),
+            name: String::from("Faculty"),
+        }],
+        groups: groups.to_vec(),
+        timetables: timetables.to_vec(),
+    }
+}
+
+fn group(uuid: &str, name: &str) -> Group {
+    Group {
+        uuid: uuid.to_string(),
+        name: name.to_string(),
+        faculty: FACULTY.to_string(),
+    }
+}
+
+fn exported_bundle_is_imported_once(db: &Database) {
+    let math = event("Math", Day::Monday, 9, "ФМ 311", "Иванов И.И.");
+    let next_week = Event {
+        week_start: week() + Duration::weeks(1),
+        ..event("Physics", Day::Friday, 11, "101", "Петров П.П.")
+    };
+    let bundle = bundle(&[group(GROUP, "Group")], &[math.clone(), next_week.clone()]);
+
+    let report = db.import(&bundle).unwrap();
+    assert_eq!((report.faculties, report.groups, report.events), (1, 1, 2));
+    assert!(report.conflicts.is_empty() && report.invalid.is_empty());
+    assert_eq!(db.get_instructors().unwrap().len(), 2);
+    assert_eq!(db.get_stats().unwrap().rooms, 2);
+    assert!(db
+        .get_last_modified(Resource::Timetable(GROUP))
+        .unwrap()
+        .is_some());
+
+    let exported = db.export().unwrap();
+    assert_eq!(exported.format_version, BUNDLE_FORMAT_VERSION);
+    assert!(exported.metadata.schema_version.is_some());
+    assert_eq!(exported.faculties.len(), 1);
+    assert_eq!(exported.groups.len(), 1);
+    assert_eq!(exported.timetables, vec![math, next_week]);
+
+    let report = db.import(&exported).unwrap();
+    assert_eq!((report.faculties, report.groups, report.events), (0, 0, 0));
+    assert!(report.conflicts.is_empty() && report.invalid.is_empty());
+}
+
+fn import_reports_conflicts_and_keeps_stored_data(db: &Database) {
+    store_group(db);
+    let math = event("Math", Day::Monday, 9, "101", "A");
+    db.update_timetable(&timetable(std::slice::from_ref(&math)))
+        .unwrap();
+
+    let other = "other";
+    let mut bundle = bundle(
+        &[group(GROUP, "Renamed"), group(other, "Other")],
+        &[
+            event("Physics", Day::Monday, 9, "101", "A"),
+            Event {
+                student_group: other.to_string(),
+                ..math.clone()
+            },
+        ],
+    );
+    let report = db.import(&bundle).unwrap();
+    assert_eq!((report.faculties, report.groups, report.events), (0, 1, 1));
+    assert_eq!(report.conflicts.len(), 2, "{:?}", report.conflicts);
+    assert!(report.invalid.is_empty());
+    assert_eq!(
+        db.get_group(&GROUP.to_string()).unwrap().unwrap().name,
+        "Group"
+    );
+    assert_eq!(
+        db.get_timetable_for_group(&GROUP.to_string(), &week())
+            .unwrap()[&Day::Monday],
+        vec![math]
+    );
+
+    // Groups of a conflicting faculty are skipped along with their timetables
+    bundle.faculties[0].uuid = String::from("renamed");
+    bundle.groups = vec![Group {
+        faculty: String::from("renamed"),
+        ..group("another", "Another")
+    }];
+    bundle.timetables = vec![Event {
+        student_group: String::from("another"),
+        ..event("Math", Day::Monday, 9, "101", "A")
+    }];
+    let report = db.import(&bundle).unwrap();
+    assert_eq!((report.faculties, report.groups, report.events), (0, 0, 0));
+    assert_eq!(report.conflicts.len(), 3, "{:?}", report.conflicts);
+    assert!(db.get_group(&String::from("another")).unwrap().is_none());
+}
+
+fn import_with_unknown_references_stores_nothing(db: &Database) {
+    let mut bundle = bundle(
+        &[Group {
+            faculty: String::from("missing"),
+            ..group(GROUP, "Group")
+        }],
+        &[Event {
+            student_group: String::from("missing"),
+            ..event("Math", Day::Monday, 9, "101", "A")
+        }],
+    );
+
+    let report = db.import(&bundle).unwrap();
+    assert_eq!(report.invalid.len(), 2, "{:?}", report.invalid);
+    assert_eq!((report.faculties, report.groups, report.events), (0, 0, 0));
+    assert!(db.get_faculties().unwrap().is_empty());
+
+    bundle.groups[0].faculty = FACULTY.to_string();
+    bundle.timetables[0].student_group = GROUP.to_string();
+    assert!(db.import(&bundle).unwrap().invalid.is_empty());
+    assert_eq!(db.get_faculties().unwrap().len(), 1);
+}
@@ -0,0 +1,71 @@
+//! Formats of the exported database: a JSON bundle or a CSV file per table
+
+use std::io::{Read, Write};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::database::models::Bundle;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Table {
+    Faculties,
+    Groups,
+    Timetables,
+}
+
+impl Table {
+    pub const ALL: [Self; 3] = [Self::Faculties, Self::Groups, Self::Timetables];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Faculties => "faculties.csv",
+            Self::Groups => "groups.csv",
+            Self::Timetables => "timetables.csv",
+        }
+    }
+}
+
+/// Version and metadata of a CSV export, written next to the tables
+pub const METADATA_FILE: &str = "metadata.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct CsvMetadata {
+    pub format_version: u32,
+    pub metadata: crate::database::models::BundleMetadata,
+}
+
+fn write_rows<T: Serialize>(rows: &[T], writer: impl Write) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes rows of the table with a header, absent values are written as empty fields
+pub fn write_csv(bundle: &Bundle, table: Table, writer: impl Write) -> csv::Result<()> {
+    match table {
+        Table::Faculties => write_rows(&bundle.faculties, writer),
+        Table::Groups => write_rows(&bundle.groups, writer),
+        Table::Timetables => write_rows(&bundle.timetables, writer),
+    }
+}
+
+/// Reads rows written by [`write_csv`]
+pub fn read_csv<T: DeserializeOwned>(reader: impl Read) -> csv::Result<Vec<T>> {
+    csv::Reader::from_reader(reader).deserialize().collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,86 @@
+use chrono::NaiveDate;
+
+use super::*;
+use crate::database::models::{
+    BundleMetadata, Day, Event, EventKind, Faculty, Group, BUNDLE_FORMAT_VERSION,
+};
+
+fn bundle() -> Bundle {
+    let event = Event::builder()
+        .name("Математический анализ")
+        .day(Day::Tuesday)
+        .week(NaiveDate::from_ymd_opt(2023, 6, 12).unwrap())
+        .room("ФМ 311, корпус 2")
+        .teacher("Иванов И.И.")
+        .kind(EventKind::Lecture)
+        .build();
+    Bundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        metadata: BundleMetadata {
+            exported_at: NaiveDate::from_ymd_opt(2023, 6, 14)
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap(),
+            backend_version: String::from("0.1.0"),
+            schema_version: None,
+        },
+        faculties: vec![Faculty {
+            uuid: String::from("faculty"),
+            name: String::from("Факультет физико-математических и естественных наук"),
+        }],
+        groups: vec![Group {
+            uuid: String::from("group"),
+            name: String::from("НПИбд-01-21"),
+            faculty: String::from("faculty"),
+        }],
+        timetables: vec![
+            event.clone(),
+            Event {
+                day: Day::Saturday,
+                kind: None,
+                subgroup: Some(String::from("1")),
+                link: Some(String::from("https://example.com/meeting")),
+                ..event
+            },
+        ],
+    }
+}
+
+fn csv(table: Table) -> String {
+    let mut output = vec![];
+    write_csv(&bundle(), table, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn writes_header_and_empty_fields_for_absent_values() {
+    let timetables = csv(Table::Timetables);
+    let mut lines = timetables.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "name,day,start_time,end_time,student_group,week_start,room,teacher,kind,subgroup,link"
+    );
+    assert_eq!(
+        lines.next().unwrap(),
+        "Математический анализ,Tuesday,09:00:00,10:20:00,group,2023-06-12,\
+        \"ФМ 311, корпус 2\",Иванов И.И.,Lecture,,"
+    );
+}
+
+#[test]
+fn reads_written_tables() {
+    let bundle = bundle();
+    let faculties: Vec<Faculty> = read_csv(csv(Table::Faculties).as_bytes()).unwrap();
+    let groups: Vec<Group> = read_csv(csv(Table::Groups).as_bytes()).unwrap();
+    let timetables: Vec<Event> = read_csv(csv(Table::Timetables).as_bytes()).unwrap();
+
+    assert_eq!(faculties[0].name, bundle.faculties[0].name);
+    assert_eq!(groups[0].faculty, bundle.groups[0].faculty);
+    assert_eq!(timetables, bundle.timetables);
+}
+
+#[test]
+fn rejects_rows_with_missing_fields() {
+    let timetables = "name,day\nМатематический анализ,Tuesday\n";
+    assert!(read_csv::<Event>(timetables.as_bytes()).is_err());
+}
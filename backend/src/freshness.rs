@@ -0,0 +1,199 @@
+//! Freshness policy of the scraped data: stale data is served right away
+//! while it is scraped again in the background
+
+use std::{
+    collections::HashMap,
+    env,
+    future::Future,
+    sync::Mutex,
+    time::{self, Instant},
+};
+
+use actix_web::web;
+use chrono::{Duration, NaiveDateTime};
+
+use crate::{
+    api::ApiResult,
+    database::{models::Resource, Database},
+    routes::run_db,
+    scraping::{self, RudnClient},
+};
+
+/// How long scraped data of every kind is considered fresh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ttls {
+    pub faculties: Duration,
+    pub groups: Duration,
+    pub timetable: Duration,
+}
+
+impl Default for Ttls {
+    fn default() -> Self {
+        Self {
+            faculties: Duration::days(30),
+            groups: Duration::days(7),
+            timetable: Duration::hours(6),
+        }
+    }
+}
+
+/// Parses a TTL given in seconds, `default` is used when it is absent or invalid
+pub fn parse_ttl(name: &str, value: Option<&str>, default: Duration) -> Duration {
+    let Some(value) = value else {
+        return default;
+    };
+    match value.trim().parse::<u32>() {
+        Ok(seconds) => Duration::seconds(seconds.into()),
+        Err(e) => {
+            log::warn!("Ignoring {name}={value}: {e}");
+            default
+        }
+    }
+}
+
+impl Ttls {
+    /// Reads TTLs in seconds from `FACULTIES_TTL`, `GROUPS_TTL` and `TIMETABLE_TTL`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let ttl = |name: &str, default| parse_ttl(name, env::var(name).ok().as_deref(), default);
+        Self {
+            faculties: ttl("FACULTIES_TTL", default.faculties),
+            groups: ttl("GROUPS_TTL", default.groups),
+            timetable: ttl("TIMETABLE_TTL", default.timetable),
+        }
+    }
+
+    pub fn of(&self, resource: Resource) -> Duration {
+        match resource {
+            Resource::Faculties => self.faculties,
+            Resource::Groups(_) => self.groups,
+            Resource::Timetable(_) => self.timetable,
+        }
+    }
+
+    /// Data which was never scraped, e.g. stored before the scrapes were tracked, is stale
+    pub fn is_stale(
+        &self,
+        resource: Resource,
+        scraped_at: Option<NaiveDateTime>,
+        now: NaiveDateTime,
+    ) -> bool {
+        scraped_at.is_none_or(|scraped_at| now - scraped_at > self.of(resource))
+    }
+}
+
+/// A failed refresh is not attempted again for this long,
+/// so requests for a resource the RUDN website fails to serve do not hammer it
+const RETRY_AFTER: time::Duration = time::Duration::from_secs(60);
+
+/// Background refreshes by the keys of their resources,
+/// so concurrent requests for the same stale resource spawn a single refresh
+#[derive(Debug, Default)]
+pub struct Refreshes(Mutex<HashMap<String, Refresh>>);
+
+#[derive(Debug, Clone, Copy)]
+enum Refresh {
+    Running,
+    FailedAt(Instant),
+}
+
+impl Refreshes {
+    /// Marks the refresh as running, returns false if it is already running
+    /// or failed less than `RETRY_AFTER` ago
+    pub fn start(&self, key: &str, now: Instant) -> bool {
+        let mut refreshes = self.0.lock().unwrap();
+        match refreshes.get(key) {
+            Some(Refresh::Running) => false,
+            Some(Refresh::FailedAt(failed_at)) if now - *failed_at < RETRY_AFTER => false,
+            _ => {
+                refreshes.insert(key.to_string(), Refresh::Running);
+                true
+            }
+        }
+    }
+
+    pub fn finish(&self, key: &str, succeeded: bool, now: Instant) {
+        let mut refreshes = self.0.lock().unwrap();
+        if succeeded {
+            refreshes.remove(key);
+        } else {
+            refreshes.insert(key.to_string(), Refresh::FailedAt(now));
+        }
+    }
+}
+
+/// Scrapes the resource again without waiting for it, failures are only logged.
+/// Nothing is spawned while the resource is being refreshed or shortly after its refresh failed
+pub fn refresh_in_background(
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    refreshes: web::Data<Refreshes>,
+    resource: Resource,
+) {
+    let key = resource.key();
+    if !refreshes.start(&key, Instant::now()) {
+        log::debug!("Not refreshing {key}, it is being refreshed or failed recently");
+        return;
+    }
+    log::debug!("Refreshing stale {key}");
+    match resource {
+        Resource::Faculties => {
+            let refresh = refresh_faculties(db, client);
+            actix_web::rt::spawn(finish(refreshes, key, refresh));
+        }
+        Resource::Groups(faculty) => {
+            let refresh = refresh_groups(db, client, faculty.to_string());
+            actix_web::rt::spawn(finish(refreshes, key, refresh));
+        }
+        Resource::Timetable(group) => {
+            let refresh = refresh_timetable(db, client, group.to_string());
+            actix_web::rt::spawn(finish(refreshes, key, refresh));
+        }
+    }
+}
+
+async fn finish(
+    refreshes: web::Data<Refreshes>,
+    key: String,
+    refresh: impl Future<Output = ApiResult<()>>,
+) {
+    let result = refresh.await;
+    if let Err(e) = &result {
+        log::warn!("Could not refresh {key}: {e}");
+    }
+    refreshes.finish(&key, result.is_ok(), Instant::now());
+}
+
+/// Stores new faculties and renames the stored ones, disappeared faculties are kept
+async fn refresh_faculties(
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+) -> ApiResult<()> {
+    let faculties = scraping::scrape_faculties(client.get_ref()).await?;
+    run_db(&db, move |db| db.update_faculties(&faculties)).await?;
+    Ok(())
+}
+
+/// Stores new groups of the faculty and renames the stored ones, disappeared groups are kept
+async fn refresh_groups(
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    faculty: String,
+) -> ApiResult<()> {
+    let groups = scraping::scrape_group(client.get_ref(), &faculty).await?;
+    run_db(&db, move |db| db.update_groups(&groups)).await?;
+    Ok(())
+}
+
+async fn refresh_timetable(
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    group: String,
+) -> ApiResult<()> {
+    let timetable = scraping::scrape_timetable(client.get_ref(), &group).await?;
+    run_db(&db, move |db| db.update_timetable(&timetable)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use chrono::NaiveDate;
+
+use super::*;
+
+fn at(hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2023, 6, 24)
+        .unwrap()
+        .and_hms_opt(hour, 0, 0)
+        .unwrap()
+}
+
+#[test]
+fn data_within_ttl_is_fresh() {
+    let ttls = Ttls::default();
+    assert!(!ttls.is_stale(Resource::Timetable("group"), Some(at(6)), at(12)));
+    assert!(ttls.is_stale(Resource::Timetable("group"), Some(at(5)), at(12)));
+}
+
+#[test]
+fn ttl_depends_on_resource() {
+    let ttls = Ttls::default();
+    assert!(!ttls.is_stale(Resource::Faculties, Some(at(0)), at(23)));
+    assert!(!ttls.is_stale(Resource::Groups("faculty"), Some(at(0)), at(23)));
+    assert!(ttls.is_stale(Resource::Timetable("group"), Some(at(0)), at(23)));
+}
+
+#[test]
+fn never_scraped_data_is_stale() {
+    assert!(Ttls::default().is_stale(Resource::Faculties, None, at(0)));
+}
+
+#[test]
+fn ttl_is_parsed_in_seconds() {
+    let default = Duration::hours(1);
+    assert_eq!(parse_ttl("TTL", Some("90"), default), Duration::seconds(90));
+    assert_eq!(parse_ttl("TTL", None, default), default);
+    assert_eq!(parse_ttl("TTL", Some("an hour"), default), default);
+}
+
+#[test]
+fn refresh_is_not_repeated_while_running_or_after_a_failure() {
+    let refreshes = Refreshes::default();
+    let now = Instant::now();
+    assert!(refreshes.start("timetable:group", now));
+    assert!(!refreshes.start("timetable:group", now));
+    assert!(refreshes.start("timetable:other", now));
+
+    refreshes.finish("timetable:group", false, now);
+    assert!(!refreshes.start("timetable:group", now + RETRY_AFTER / 2));
+    assert!(refreshes.start("timetable:group", now + RETRY_AFTER));
+
+    refreshes.finish("timetable:group", true, now + RETRY_AFTER);
+    assert!(refreshes.start("timetable:group", now + RETRY_AFTER));
+}
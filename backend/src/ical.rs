@@ -0,0 +1,268 @@
+//! Rendering of stored timetables as RFC 5545 iCalendar feeds
+
+use std::collections::BTreeMap;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::{calendar::AcademicCalendar, database::models::*};
+
+/// Timetables of the RUDN University are always in Moscow time
+const TIMEZONE: &str = "Europe/Moscow";
+
+/// Moscow has not observed daylight saving time since 2014, so a single standard rule suffices
+const VTIMEZONE: &str = "BEGIN:VTIMEZONE\r
+TZID:Europe/Moscow\r
+BEGIN:STANDARD\r
+DTSTART:19700101T000000\r
+TZOFFSETFROM:+0300\r
+TZOFFSETTO:+0300\r
+TZNAME:MSK\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+/// Maximum length of a content line in octets, excluding the line break
+const MAX_LINE_LENGTH: usize = 75;
+
+/// Renders events as a VCALENDAR named `calendar_name`.
+/// Occurrences of the same class make a single VEVENT repeating with a weekly RRULE
+/// or, when the weeks are irregular, with an RDATE list.
+/// Occurrences which differ from the first one in their details override it with a RECURRENCE-ID.
+/// Events on the days off of the academic calendar are left out
+pub fn render_calendar(
+    calendar_name: &str,
+    events: &[Event],
+    academic_calendar: &AcademicCalendar,
+) -> String {
+    let mut calendar = String::new();
+    push_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_line(&mut calendar, "VERSION:2.0");
+    push_line(&mut calendar, "PRODID:-//RUDN Lab//Timetable//RU");
+    push_line(&mut calendar, "CALSCALE:GREGORIAN");
+    push_line(&mut calendar, "METHOD:PUBLISH");
+    push_line(
+        &mut calendar,
+        &format!("X-WR-CALNAME:{}", escape(calendar_name)),
+    );
+    push_line(&mut calendar, &format!("X-WR-TIMEZONE:{TIMEZONE}"));
+    calendar.push_str(VTIMEZONE);
+
+    let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let events = events
+        .iter()
+        .filter(|event| academic_calendar.takes_place(event));
+    for occurrences in recurring_classes(events) {
+        let first = occurrences[0];
+        let uid = uid(first);
+        let dates = occurrences
+            .iter()
+            .map(|event| event.date())
+            .collect::<Vec<_>>();
+        let recurrence = recurrence(&dates, first.start_time);
+        push_event(&mut calendar, &uid, first, recurrence.as_deref(), &dtstamp);
+        for occurrence in occurrences[1..]
+            .iter()
+            .filter(|occurrence| !same_details(occurrence, first))
+        {
+            let recurrence_id = format!(
+                "RECURRENCE-ID;TZID={TIMEZONE}:{}",
+                format_local(occurrence.date().and_time(occurrence.start_time))
+            );
+            push_event(
+                &mut calendar,
+                &uid,
+                occurrence,
+                Some(&recurrence_id),
+                &dtstamp,
+            );
+        }
+    }
+
+    push_line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+/// Groups events describing the same class, returns the occurrences of every class
+/// sorted by their dates, one occurrence per date
+fn recurring_classes<'a>(events: impl IntoIterator<Item = &'a Event>) -> Vec<Vec<&'a Event>> {
+    let mut classes: BTreeMap<String, Vec<&Event>> = BTreeMap::new();
+    for event in events {
+        classes.entry(class_key(event)).or_default().push(event);
+    }
+
+    classes
+        .into_values()
+        .map(|mut occurrences| {
+            occurrences.sort_by_key(|event| event.date());
+            occurrences.dedup_by_key(|event| event.date());
+            occurrences
+        })
+        .collect()
+}
+
+/// Key identifying a class regardless of the week it takes place in.
+/// Only the fields which do not change when a class is moved to another room
+/// or given to another teacher are used
+fn class_key(event: &Event) -> String {
+    format!(
+        "{}|{}|{:?}|{}|{:?}|{:?}",
+        event.student_group, event.name, event.day, event.start_time, event.kind, event.subgroup
+    )
+}
+
+/// Whether two occurrences of a class can be described by the same VEVENT
+fn same_details(event: &Event, other: &Event) -> bool {
+    event.end_time == other.end_time
+        && event.room == other.room
+        && event.teacher == other.teacher
+        && event.link == other.link
+}
+
+/// Splits sorted dates into runs repeating with a constant step,
+/// returns the first date, the step in weeks and the number of occurrences of every run.
+/// Alternating week classes become runs with a step of two weeks
+fn weekly_runs(dates: &[NaiveDate]) -> Vec<(NaiveDate, i64, usize)> {
+    let mut runs = vec![];
+    let mut i = 0;
+    while i < dates.len() {
+        let first = dates[i];
+        let step = dates
+            .get(i + 1)
+            .map_or(1, |next| (*next - first).num_weeks());
+        let mut count = 1;
+        while dates
+            .get(i + count)
+            .is_some_and(|date| (*date - dates[i + count - 1]).num_weeks() == step)
+        {
+            count += 1;
+        }
+        runs.push((first, step, count));
+        i += count;
+    }
+    runs
+}
+
+/// Recurrence of a class taking place on sorted `dates`: an RRULE when the dates
+/// repeat with a constant step, an RDATE listing the dates after the first one otherwise
+fn recurrence(dates: &[NaiveDate], start_time: NaiveTime) -> Option<String> {
+    match weekly_runs(dates)[..] {
+        [] | [(_, _, 1)] => None,
+        [(_, step, count)] => Some(format!("RRULE:FREQ=WEEKLY;INTERVAL={step};COUNT={count}")),
+        _ => Some(format!(
+            "RDATE;TZID={TIMEZONE}:{}",
+            dates[1..]
+                .iter()
+                .map(|date| format_local(date.and_time(start_time)))
+                .collect::<Vec<_>>()
+                .join(",")
+        )),
+    }
+}
+
+fn format_local(datetime: NaiveDateTime) -> String {
+    datetime.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Writes a VEVENT of `event` with either the recurrence of its class
+/// or the RECURRENCE-ID of the occurrence it overrides
+fn push_event(
+    calendar: &mut String,
+    uid: &str,
+    event: &Event,
+    recurrence: Option<&str>,
+    dtstamp: &str,
+) {
+    let date = event.date();
+
+    push_line(calendar, "BEGIN:VEVENT");
+    push_line(calendar, &format!("UID:{uid}"));
+    push_line(calendar, &format!("DTSTAMP:{dtstamp}"));
+    push_line(
+        calendar,
+        &format!(
+            "DTSTART;TZID={TIMEZONE}:{}",
+            format_local(date.and_time(event.start_time))
+        ),
+    );
+    push_line(
+        calendar,
+        &format!(
+            "DTEND;TZID={TIMEZONE}:{}",
+            format_local(date.and_time(event.end_time))
+        ),
+    );
+    if let Some(recurrence) = recurrence {
+        push_line(calendar, recurrence);
+    }
+    push_line(calendar, &format!("SUMMARY:{}", escape(&event.name)));
+    if let Some(room) = &event.room {
+        push_line(calendar, &format!("LOCATION:{}", escape(room)));
+    }
+
+    let description = [
+        event.kind.map(|kind| format!("{kind:?}")),
+        event.teacher.clone(),
+        event
+            .subgroup
+            .as_ref()
+            .map(|subgroup| format!("Subgroup {subgroup}")),
+        event.link.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n");
+    if !description.is_empty() {
+        push_line(calendar, &format!("DESCRIPTION:{}", escape(&description)));
+    }
+    if let Some(link) = &event.link {
+        push_line(calendar, &format!("URL:{link}"));
+    }
+    push_line(calendar, "END:VEVENT");
+}
+
+/// UID stays the same between renders as long as the stable fields of the class do not change,
+/// so moved classes and pruned weeks update the events already imported by calendar clients.
+/// FNV-1a is used because the std hasher is not guaranteed to be stable between Rust releases
+fn uid(event: &Event) -> String {
+    let hash = class_key(event)
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    format!("{hash:016x}@timetable.rudn-lab.ru")
+}
+
+/// Escapes a TEXT property value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends a content line folded at 75 octets without splitting multi-byte characters
+fn push_line(calendar: &mut String, line: &str) {
+    let mut line_length = 0;
+    for c in line.chars() {
+        if line_length + c.len_utf8() > MAX_LINE_LENGTH {
+            calendar.push_str("\r\n ");
+            // The leading space of a continuation line counts towards its length
+            line_length = 1;
+        }
+        calendar.push(c);
+        line_length += c.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests;
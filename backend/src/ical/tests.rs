@@ -0,0 +1,186 @@
+use super::*;
+
+fn date(month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+}
+
+fn math(week_start: NaiveDate) -> Event {
+    Event::builder()
+        .week(week_start)
+        .room("ФМ 311")
+        .teacher("Иванов И.И.")
+        .build()
+}
+
+/// Content lines of the calendar with the folded ones unfolded
+fn unfolded(calendar: &str) -> Vec<String> {
+    calendar
+        .replace("\r\n ", "")
+        .split("\r\n")
+        .map(String::from)
+        .collect()
+}
+
+fn properties<'a>(lines: &'a [String], name: &str) -> Vec<&'a str> {
+    lines
+        .iter()
+        .filter_map(|line| line.strip_prefix(name))
+        .collect()
+}
+
+#[test]
+fn text_values_are_escaped() {
+    assert_eq!(
+        escape("Лекция; ауд. 1, 2\\3\r\nонлайн"),
+        "Лекция\\; ауд. 1\\, 2\\\\3\\nонлайн"
+    );
+}
+
+#[test]
+fn long_lines_are_folded_without_splitting_characters() {
+    let line = format!("SUMMARY:{}", "Математический анализ ".repeat(8));
+    let mut calendar = String::new();
+    push_line(&mut calendar, &line);
+
+    assert!(calendar.ends_with("\r\n"));
+    let physical_lines = calendar.trim_end_matches("\r\n").split("\r\n");
+    for (i, physical_line) in physical_lines.enumerate() {
+        assert!(physical_line.len() <= MAX_LINE_LENGTH, "{physical_line:?}");
+        assert_eq!(i > 0, physical_line.starts_with(' '));
+    }
+    assert_eq!(unfolded(&calendar), [line, String::new()]);
+}
+
+#[test]
+fn dates_are_split_into_runs_with_a_constant_step() {
+    let weekly = [date(9, 7), date(9, 14), date(9, 21)];
+    assert_eq!(weekly_runs(&weekly), [(date(9, 7), 1, 3)]);
+
+    let alternating = [date(9, 7), date(9, 21), date(10, 5)];
+    assert_eq!(weekly_runs(&alternating), [(date(9, 7), 2, 3)]);
+
+    // A missed week breaks the run, the rest makes a run of its own
+    let with_gap = [date(9, 7), date(9, 14), date(9, 28), date(10, 5)];
+    assert_eq!(
+        weekly_runs(&with_gap),
+        [(date(9, 7), 1, 2), (date(9, 28), 1, 2)]
+    );
+
+    assert_eq!(weekly_runs(&[date(9, 7)]), [(date(9, 7), 1, 1)]);
+    assert!(weekly_runs(&[]).is_empty());
+}
+
+#[test]
+fn repeating_classes_become_recurring_events() {
+    let events = [
+        math(date(9, 7)),
+        math(date(9, 21)),
+        math(date(10, 5)),
+        Event::builder()
+            .name("Физика")
+            .day(Day::Friday)
+            .week(date(9, 7))
+            .build(),
+    ];
+
+    let calendar = render_calendar("НПИбд-01-22", &events, &AcademicCalendar::default());
+    let lines = unfolded(&calendar);
+    assert_eq!(lines.first().unwrap(), "BEGIN:VCALENDAR");
+    assert_eq!(properties(&lines, "BEGIN:VEVENT").len(), 2);
+    assert_eq!(
+        properties(&lines, "RRULE:"),
+        ["FREQ=WEEKLY;INTERVAL=2;COUNT=3"]
+    );
+    assert!(properties(&lines, "DTSTART;TZID=Europe/Moscow:").contains(&"20260907T090000"));
+    assert!(properties(&lines, "DTSTART;TZID=Europe/Moscow:").contains(&"20260911T090000"));
+    assert_eq!(properties(&lines, "LOCATION:"), ["ФМ 311"]);
+}
+
+#[test]
+fn uids_are_stable_between_renders() {
+    let events = [math(date(9, 7)), math(date(9, 14))];
+    let uids = |events: &[Event]| {
+        let calendar = render_calendar("group", events, &AcademicCalendar::default());
+        properties(&unfolded(&calendar), "UID:")
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(uids(&events), uids(&events));
+    assert_eq!(uids(&events).len(), 1);
+    // Neither a moved class nor a pruned first week changes the UID
+    let moved = [Event::builder()
+        .week(date(9, 7))
+        .room("101")
+        .teacher("Петров П.П.")
+        .build()];
+    assert_eq!(uids(&events), uids(&moved));
+    assert_eq!(uids(&events), uids(&events[1..]));
+    let later = [Event::builder().week(date(9, 7)).starts_at(13).build()];
+    assert_ne!(uids(&events), uids(&later));
+}
+
+#[test]
+fn irregular_weeks_are_listed_in_rdate() {
+    let events = [
+        math(date(9, 7)),
+        math(date(9, 14)),
+        math(date(9, 28)),
+        math(date(10, 5)),
+    ];
+
+    let calendar = render_calendar("group", &events, &AcademicCalendar::default());
+    let lines = unfolded(&calendar);
+    assert_eq!(properties(&lines, "BEGIN:VEVENT").len(), 1);
+    assert!(properties(&lines, "RRULE:").is_empty());
+    assert_eq!(
+        properties(&lines, "RDATE;TZID=Europe/Moscow:"),
+        ["20260914T090000,20260928T090000,20261005T090000"]
+    );
+}
+
+#[test]
+fn changed_occurrences_override_the_recurring_event() {
+    let moved = Event::builder()
+        .week(date(9, 14))
+        .room("101")
+        .teacher("Иванов И.И.")
+        .build();
+    let events = [math(date(9, 7)), moved, math(date(9, 21))];
+
+    let calendar = render_calendar("group", &events, &AcademicCalendar::default());
+    let lines = unfolded(&calendar);
+    let uids = properties(&lines, "UID:");
+    assert_eq!(uids.len(), 2);
+    assert_eq!(uids[0], uids[1]);
+    assert_eq!(
+        properties(&lines, "RRULE:"),
+        ["FREQ=WEEKLY;INTERVAL=1;COUNT=3"]
+    );
+    assert_eq!(
+        properties(&lines, "RECURRENCE-ID;TZID=Europe/Moscow:"),
+        ["20260914T090000"]
+    );
+    assert_eq!(properties(&lines, "LOCATION:"), ["ФМ 311", "101"]);
+}
+
+#[test]
+fn classes_on_days_off_are_left_out() {
+    let academic_calendar =
+        AcademicCalendar::parse(r#"{"holidays": [{"name": "Holiday", "start": "2026-09-14"}]}"#)
+            .unwrap();
+    let events = [math(date(9, 7)), math(date(9, 14)), math(date(9, 21))];
+
+    let calendar = render_calendar("group", &events, &academic_calendar);
+    let lines = unfolded(&calendar);
+    // The weeks around the holiday are two weeks apart
+    assert_eq!(
+        properties(&lines, "DTSTART;TZID=Europe/Moscow:"),
+        ["20260907T090000"]
+    );
+    assert_eq!(
+        properties(&lines, "RRULE:"),
+        ["FREQ=WEEKLY;INTERVAL=2;COUNT=2"]
+    );
+}
@@ -0,0 +1,131 @@
+//! Events of the stored weeks resolved into lessons on concrete dates and times
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    calendar::{AcademicCalendar, CalendarDay},
+    database::models::*,
+};
+
+/// Timetables are in Moscow time, which has been UTC+3 all year round since 2014
+pub fn moscow() -> FixedOffset {
+    FixedOffset::east_opt(3 * 3600).unwrap()
+}
+
+/// Current time in Moscow
+pub fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&moscow())
+}
+
+/// Event taking place on a date
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct Lesson {
+    #[serde(flatten)]
+    pub event: Event,
+    pub date: NaiveDate,
+    pub starts_at: DateTime<FixedOffset>,
+    pub ends_at: DateTime<FixedOffset>,
+    /// Minutes left until the lesson starts, negative once it has started
+    pub starts_in_minutes: i64,
+    /// Whether the lesson is taking place right now
+    pub in_progress: bool,
+}
+
+impl Lesson {
+    pub fn new(event: Event, now: DateTime<FixedOffset>) -> Self {
+        let date = event.date();
+        let at = |time| {
+            date.and_time(time)
+                .and_local_timezone(moscow())
+                .single()
+                .expect("Moscow time has a fixed offset")
+        };
+        let (starts_at, ends_at) = (at(event.start_time), at(event.end_time));
+        Self {
+            date,
+            starts_at,
+            ends_at,
+            starts_in_minutes: (starts_at - now).num_minutes(),
+            in_progress: starts_at <= now && now < ends_at,
+            event,
+        }
+    }
+}
+
+/// Lessons of a single date along with its academic calendar
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DayLessons {
+    #[serde(flatten)]
+    pub calendar: CalendarDay,
+    /// Lessons in the order they start, none on the days off
+    pub lessons: Vec<Lesson>,
+}
+
+/// Lessons which have not ended yet
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Upcoming {
+    /// Lessons in the order they start, the one in progress comes first
+    pub lessons: Vec<Lesson>,
+    /// Minutes left until the next lesson which has not started yet
+    pub next_starts_in_minutes: Option<i64>,
+}
+
+fn sorted(mut lessons: Vec<Lesson>) -> Vec<Lesson> {
+    lessons.sort_by(|a, b| {
+        (a.starts_at, &a.event.name, &a.event.subgroup).cmp(&(
+            b.starts_at,
+            &b.event.name,
+            &b.event.subgroup,
+        ))
+    });
+    lessons
+}
+
+/// Lessons on the date out of the events of its week,
+/// events on the days off of the academic calendar are left out
+pub fn on_date(
+    date: NaiveDate,
+    events: Vec<Event>,
+    academic_calendar: &AcademicCalendar,
+    now: DateTime<FixedOffset>,
+) -> DayLessons {
+    let lessons = events
+        .into_iter()
+        .filter(|event| event.date() == date && academic_calendar.takes_place(event))
+        .map(|event| Lesson::new(event, now))
+        .collect();
+    DayLessons {
+        calendar: academic_calendar.day(date),
+        lessons: sorted(lessons),
+    }
+}
+
+/// First `count` lessons which have not ended by `now`,
+/// events on the days off of the academic calendar are left out
+pub fn upcoming(
+    events: Vec<Event>,
+    academic_calendar: &AcademicCalendar,
+    now: DateTime<FixedOffset>,
+    count: usize,
+) -> Upcoming {
+    let lessons = events
+        .into_iter()
+        .filter(|event| academic_calendar.takes_place(event))
+        .map(|event| Lesson::new(event, now))
+        .filter(|lesson| lesson.ends_at > now)
+        .collect();
+    let mut lessons = sorted(lessons);
+    lessons.truncate(count);
+    Upcoming {
+        next_starts_in_minutes: lessons
+            .iter()
+            .find(|lesson| lesson.starts_at > now)
+            .map(|lesson| lesson.starts_in_minutes),
+        lessons,
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,100 @@
+use super::*;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+/// Moment of the day in Moscow time
+fn at(day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+    date(day)
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+        .and_local_timezone(moscow())
+        .unwrap()
+}
+
+fn event(name: &str, week: u32, day: Day, hour: u32) -> Event {
+    Event::builder()
+        .name(name)
+        .week(date(week))
+        .day(day)
+        .starts_at(hour)
+        .build()
+}
+
+#[test]
+fn lessons_are_dated_in_moscow_time() {
+    let lesson = Lesson::new(event("Math", 5, Day::Wednesday, 9), at(7, 8, 15));
+
+    assert_eq!(lesson.date, date(7));
+    assert_eq!(lesson.starts_at.to_rfc3339(), "2026-10-07T09:00:00+03:00");
+    assert_eq!(lesson.ends_at.to_rfc3339(), "2026-10-07T10:20:00+03:00");
+    assert_eq!(lesson.starts_in_minutes, 45);
+    assert!(!lesson.in_progress);
+
+    let lesson = Lesson::new(lesson.event, at(7, 9, 30));
+    assert_eq!(lesson.starts_in_minutes, -30);
+    assert!(lesson.in_progress);
+}
+
+#[test]
+fn lessons_of_the_date_are_ordered_by_start() {
+    let events = vec![
+        event("Physics", 5, Day::Wednesday, 13),
+        event("Math", 5, Day::Wednesday, 9),
+        event("History", 5, Day::Thursday, 9),
+    ];
+
+    let day = on_date(date(7), events, &AcademicCalendar::default(), at(7, 8, 0));
+    assert!(day.calendar.study_day);
+    let names = day
+        .lessons
+        .iter()
+        .map(|lesson| lesson.event.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["Math", "Physics"]);
+}
+
+#[test]
+fn holidays_have_no_lessons() {
+    let academic_calendar =
+        AcademicCalendar::parse(r#"{"holidays": [{"name": "Holiday", "start": "2026-10-07"}]}"#)
+            .unwrap();
+    let events = vec![event("Math", 5, Day::Wednesday, 9)];
+
+    let day = on_date(date(7), events.clone(), &academic_calendar, at(7, 8, 0));
+    assert!(!day.calendar.study_day);
+    assert!(day.lessons.is_empty());
+    assert!(upcoming(events, &academic_calendar, at(5, 8, 0), 5)
+        .lessons
+        .is_empty());
+}
+
+#[test]
+fn upcoming_lessons_start_with_the_one_in_progress() {
+    let events = vec![
+        event("Next week", 12, Day::Monday, 9),
+        event("Ended", 5, Day::Wednesday, 9),
+        event("Later", 5, Day::Friday, 9),
+        event("In progress", 5, Day::Wednesday, 11),
+        event("Next", 5, Day::Wednesday, 13),
+    ];
+
+    let upcoming = upcoming(events, &AcademicCalendar::default(), at(7, 12, 0), 3);
+    let names = upcoming
+        .lessons
+        .iter()
+        .map(|lesson| lesson.event.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["In progress", "Next", "Later"]);
+    assert_eq!(upcoming.next_starts_in_minutes, Some(60));
+}
+
+#[test]
+fn nothing_is_upcoming_after_the_last_lesson() {
+    let events = vec![event("Math", 5, Day::Wednesday, 9)];
+
+    let upcoming = upcoming(events, &AcademicCalendar::default(), at(8, 9, 0), 5);
+    assert!(upcoming.lessons.is_empty());
+    assert_eq!(upcoming.next_starts_in_minutes, None);
+}
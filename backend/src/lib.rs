@@ -1,42 +1,145 @@
 use actix_web::{middleware::Logger, services, web, App, HttpServer};
 use database::Database;
 use delay_timer::prelude::DelayTimerBuilder;
-use std::{
-    net::Ipv4Addr,
-    sync::{Arc, Mutex},
-};
+use scraping::RudnClient;
+use std::{net::Ipv4Addr, sync::Arc};
 
+pub mod admin;
+mod api;
+mod availability;
+mod caching;
+mod calendar;
 mod database;
+mod export;
+mod freshness;
+mod ical;
+mod lessons;
+mod merging;
 mod routes;
 mod scheduling;
 mod scraping;
+mod search;
+#[cfg(test)]
+mod test_support;
+mod webhooks;
 
-pub async fn init(ip: Ipv4Addr, port: u16) -> std::io::Result<()> {
-    let db_conn = Database::new();
-    let db_conn = Arc::new(Mutex::new(db_conn));
-    let db_conn_web = web::Data::new(Arc::clone(&db_conn));
+/// Runs the HTTP server and the scheduled scraping,
+/// pending migrations are applied first unless `migrate` is unset
+pub async fn init(ip: Ipv4Addr, port: u16, migrate: bool) -> std::io::Result<()> {
+    let academic_calendar = calendar::AcademicCalendar::from_env()
+        .map_err(|e| std::io::Error::other(format!("{e:#}")))?;
+    log::info!(
+        "Academic calendar has {} semesters and {} holidays",
+        academic_calendar.semesters.len(),
+        academic_calendar.holidays.len()
+    );
 
-    run_scheduler(Arc::clone(&db_conn)).await;
+    let db = Database::from_env().map_err(std::io::Error::other)?;
+    if migrate {
+        let applied = db.run_migrations().map_err(std::io::Error::other)?;
+        for version in applied {
+            log::info!("Applied migration {version}");
+        }
+    } else {
+        match db.get_schema_version() {
+            Ok(schema) if !schema.pending.is_empty() => log::warn!(
+                "{} migrations are pending, apply them with `backend db migrate`",
+                schema.pending.len()
+            ),
+            Ok(_) => {}
+            Err(e) => log::warn!("Could not check the database schema: {e}"),
+        }
+    }
+    let client = scraping::client_from_env();
 
-    run_server(ip, port, db_conn_web).await
+    run_scheduler(db.clone(), client.clone()).await;
+
+    let ttls = freshness::Ttls::from_env();
+    log::info!(
+        "Scraped faculties, groups and timetables are refreshed after {}s, {}s and {}s",
+        ttls.faculties.num_seconds(),
+        ttls.groups.num_seconds(),
+        ttls.timetable.num_seconds()
+    );
+
+    run_server(
+        ip,
+        port,
+        web::Data::new(db),
+        web::Data::from(client),
+        web::Data::new(ttls),
+        web::Data::new(freshness::Refreshes::default()),
+        web::Data::new(academic_calendar),
+    )
+    .await
 }
 
 async fn run_server(
     ip: Ipv4Addr,
     port: u16,
-    db_conn: web::Data<Arc<Mutex<Database>>>,
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    ttls: web::Data<freshness::Ttls>,
+    refreshes: web::Data<freshness::Refreshes>,
+    academic_calendar: web::Data<calendar::AcademicCalendar>,
 ) -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
-            .app_data(db_conn.clone())
+            .app_data(db.clone())
+            .app_data(client.clone())
+            .app_data(ttls.clone())
+            .app_data(refreshes.clone())
+            .app_data(academic_calendar.clone())
+            // Malformed requests are rejected in the shape of the other errors
+            .app_data(
+                web::QueryConfig::default()
+                    .error_handler(|e, _| api::ApiError::BadRequest(e.to_string()).into()),
+            )
+            .app_data(
+                web::PathConfig::default()
+                    .error_handler(|e, _| api::ApiError::BadRequest(e.to_string()).into()),
+            )
+            .app_data(
+                web::JsonConfig::default()
+                    .error_handler(|e, _| api::ApiError::BadRequest(e.to_string()).into()),
+            )
             // https://docs.rs/actix-web/latest/actix_web/middleware/struct.Logger.html#format
             .wrap(Logger::default())
-            .service(services![
-                routes::get_index,
-                routes::get_faculties,
-                routes::get_groups,
-                routes::get_timetable
-            ])
+            .service(
+                web::scope(api::API_PREFIX)
+                    .service(services![
+                        routes::get_index,
+                        routes::get_status,
+                        routes::get_faculties,
+                        routes::get_groups,
+                        routes::search_groups,
+                        routes::get_timetable,
+                        routes::get_timetable_ics,
+                        routes::get_changes,
+                        routes::get_merged_timetable,
+                        routes::get_free_slots
+                    ])
+                    .service(services![
+                        routes::get_day,
+                        routes::get_upcoming,
+                        routes::get_calendar
+                    ])
+                    .service(services![
+                        routes::get_instructors,
+                        routes::get_instructor_timetable,
+                        routes::get_instructor_timetable_ics,
+                        routes::get_free_rooms,
+                        routes::get_room_timetable
+                    ])
+                    .service(services![
+                        routes::add_webhook,
+                        routes::get_dead_letters,
+                        routes::delete_webhook,
+                        routes::get_export,
+                        api::get_openapi
+                    ]),
+            )
+            .default_service(web::to(routes::not_found))
     })
     .bind((ip, port))
     .unwrap_or_else(|_| panic!("{ip}:{port} is already bound"))
@@ -44,10 +147,11 @@ async fn run_server(
     .await
 }
 
-async fn run_scheduler(db: Arc<Mutex<Database>>) {
+async fn run_scheduler(db: Database, client: Arc<dyn RudnClient>) {
     let timer = DelayTimerBuilder::default().build();
 
-    scheduling::schedule_scrape_faculties(&timer, db.clone());
-    scheduling::schedule_scrape_groups(&timer, db.clone());
-    scheduling::schedule_scrape_timetables(&timer, db);
+    scheduling::schedule_scrape_faculties(&timer, db.clone(), client.clone());
+    scheduling::schedule_scrape_groups(&timer, db.clone(), client.clone());
+    scheduling::schedule_scrape_timetables(&timer, db.clone(), client);
+    scheduling::schedule_deliver_webhooks(&timer, db);
 }
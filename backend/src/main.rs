@@ -1,6 +1,6 @@
 use backend::*;
-use clap::{Parser, ValueEnum};
-use std::net::Ipv4Addr;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{net::Ipv4Addr, path::PathBuf};
 
 #[derive(ValueEnum, Debug, Clone, Copy)]
 enum AddressOption {
@@ -17,22 +17,128 @@ impl From<AddressOption> for Ipv4Addr {
     }
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum FormatOption {
+    /// Single JSON bundle
+    Json,
+    /// CSV file per table along with the metadata
+    Csv,
+}
+
+impl From<FormatOption> for admin::ExportFormat {
+    fn from(value: FormatOption) -> Self {
+        match value {
+            FormatOption::Json => Self::Json,
+            FormatOption::Csv => Self::Csv,
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
-struct Args {
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Options of `serve`, which runs when no command is given
+    #[command(flatten)]
+    serve: ServeArgs,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
     #[arg(value_enum, short, long, default_value_t = AddressOption::Local)]
     address: AddressOption,
 
     #[arg(short, long, default_value_t = 8080)]
     port: u16,
+
+    /// Do not apply pending migrations on startup
+    #[arg(long)]
+    no_migrate: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the HTTP server along with the scheduled scraping
+    Serve(ServeArgs),
+    /// Scrape the RUDN website and store the data, pending migrations are applied first
+    #[command(subcommand)]
+    Scrape(ScrapeCommand),
+    /// Maintain the database
+    #[command(subcommand)]
+    Db(DbCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ScrapeCommand {
+    /// Every faculty of the university
+    Faculties,
+    /// Student groups of every stored faculty
+    Groups,
+    /// Timetable of the stored group
+    Timetable { group_uuid: String },
+    /// Faculties, their groups and timetables of every group
+    All,
+}
+
+impl From<ScrapeCommand> for admin::ScrapeTarget {
+    fn from(value: ScrapeCommand) -> Self {
+        match value {
+            ScrapeCommand::Faculties => Self::Faculties,
+            ScrapeCommand::Groups => Self::Groups,
+            ScrapeCommand::Timetable { group_uuid } => Self::Timetable(group_uuid),
+            ScrapeCommand::All => Self::All,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Apply pending migrations
+    Migrate,
+    /// Write stored faculties, groups and events as a JSON bundle or as CSV files
+    Export {
+        #[arg(value_enum, short, long, default_value_t = FormatOption::Json)]
+        format: FormatOption,
+        /// File to write the bundle to instead of stdout, directory to write CSV files to
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Import faculties, groups and events exported by `db export`
+    Import {
+        /// JSON bundle or directory of CSV files
+        input: PathBuf,
+    },
+    /// Print the amount of the stored data
+    Stats,
+    /// Delete old timetables and timetable changes
+    Prune {
+        /// Number of weeks before the current one to keep
+        #[arg(long, default_value_t = 8)]
+        keep_weeks: u32,
+    },
 }
 
 #[actix_web::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> anyhow::Result<()> {
     // access logs are printed with the INFO level so ensure it is enabled by default
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    let args = Args::parse();
-    let ip = Into::<Ipv4Addr>::into(args.address);
-
-    init(ip, args.port).await
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Serve(cli.serve)) {
+        Command::Serve(args) => {
+            let ip = Into::<Ipv4Addr>::into(args.address);
+            init(ip, args.port, !args.no_migrate).await?;
+        }
+        Command::Scrape(command) => admin::scrape(command.into()).await?,
+        Command::Db(DbCommand::Migrate) => admin::migrate()?,
+        Command::Db(DbCommand::Export { format, output }) => {
+            admin::export(format.into(), output.as_deref())?
+        }
+        Command::Db(DbCommand::Import { input }) => admin::import(&input)?,
+        Command::Db(DbCommand::Stats) => admin::stats()?,
+        Command::Db(DbCommand::Prune { keep_weeks }) => admin::prune(keep_weeks)?,
+    }
+    Ok(())
 }
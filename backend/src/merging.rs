@@ -0,0 +1,98 @@
+//! Combined timetable of several student groups, e.g. for students taking electives elsewhere
+
+use std::collections::HashMap;
+
+use chrono::NaiveTime;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::database::models::*;
+
+/// Event of a merged timetable tagged with the group it comes from
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct SourcedEvent {
+    #[serde(flatten)]
+    pub event: Event,
+    /// Name of the group whose timetable contains the event
+    pub source: String,
+}
+
+/// Classes of different groups taking place at the same time
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Conflict {
+    /// Beginning of the overlap
+    pub start_time: NaiveTime,
+    /// End of the overlap
+    pub end_time: NaiveTime,
+    #[schema(value_type = Vec<SourcedEvent>, min_items = 2, max_items = 2)]
+    pub events: [SourcedEvent; 2],
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct MergedTimetable {
+    /// Events of every group ordered by their start
+    pub timetable: HashMap<Day, Vec<SourcedEvent>>,
+    /// Only days with conflicts are present
+    pub conflicts: HashMap<Day, Vec<Conflict>>,
+}
+
+/// Unions timetables of the groups and finds overlapping classes of different groups.
+/// A class shared by several of the groups, e.g. a common lecture, is not a conflict
+pub fn merge(timetables: Vec<(Group, HashMap<Day, Vec<Event>>)>) -> MergedTimetable {
+    let mut merged = MergedTimetable::default();
+    for (group, timetable) in timetables {
+        for (day, events) in timetable {
+            merged
+                .timetable
+                .entry(day)
+                .or_default()
+                .extend(events.into_iter().map(|event| SourcedEvent {
+                    event,
+                    source: group.name.clone(),
+                }));
+        }
+    }
+
+    for (day, events) in merged.timetable.iter_mut() {
+        events
+            .sort_by(|a, b| (a.event.start_time, &a.source).cmp(&(b.event.start_time, &b.source)));
+
+        let conflicts = find_conflicts(events);
+        if !conflicts.is_empty() {
+            merged.conflicts.insert(*day, conflicts);
+        }
+    }
+
+    merged
+}
+
+/// Finds overlapping pairs of events sorted by their start
+fn find_conflicts(events: &[SourcedEvent]) -> Vec<Conflict> {
+    let mut conflicts = vec![];
+    for (i, first) in events.iter().enumerate() {
+        // Later events start after this one ends, so they can not overlap it
+        for second in events[i + 1..]
+            .iter()
+            .take_while(|second| second.event.start_time < first.event.end_time)
+        {
+            if first.event.student_group == second.event.student_group
+                || is_shared_class(&first.event, &second.event)
+            {
+                continue;
+            }
+            conflicts.push(Conflict {
+                start_time: second.event.start_time,
+                end_time: first.event.end_time.min(second.event.end_time),
+                events: [first.clone(), second.clone()],
+            });
+        }
+    }
+    conflicts
+}
+
+fn is_shared_class(a: &Event, b: &Event) -> bool {
+    a.name == b.name && a.start_time == b.start_time && a.end_time == b.end_time && a.room == b.room
+}
+
+#[cfg(test)]
+mod tests;
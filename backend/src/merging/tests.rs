@@ -0,0 +1,114 @@
+use super::*;
+
+fn group(name: &str) -> Group {
+    Group {
+        uuid: format!("uuid-{name}"),
+        name: name.to_string(),
+        faculty: String::from("faculty"),
+    }
+}
+
+fn event(group: &Group, name: &str, day: Day, start: (u32, u32), end: (u32, u32)) -> Event {
+    Event::builder()
+        .name(name)
+        .group(&group.uuid)
+        .day(day)
+        .time(start, end)
+        .room("101")
+        .build()
+}
+
+fn timetable(events: Vec<Event>) -> HashMap<Day, Vec<Event>> {
+    let mut timetable: HashMap<Day, Vec<Event>> = HashMap::new();
+    for event in events {
+        timetable.entry(event.day).or_default().push(event);
+    }
+    timetable
+}
+
+#[test]
+fn unions_events_ordered_by_start_and_tagged_with_source() {
+    let (a, b) = (group("A"), group("B"));
+    let merged = merge(vec![
+        (
+            a.clone(),
+            timetable(vec![event(&a, "Физика", Day::Monday, (10, 30), (11, 50))]),
+        ),
+        (
+            b.clone(),
+            timetable(vec![
+                event(&b, "Химия", Day::Monday, (9, 0), (10, 20)),
+                event(&b, "Химия", Day::Friday, (9, 0), (10, 20)),
+            ]),
+        ),
+    ]);
+
+    let monday = merged.timetable[&Day::Monday]
+        .iter()
+        .map(|event| (event.event.name.as_str(), event.source.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(monday, [("Химия", "B"), ("Физика", "A")]);
+    assert_eq!(merged.timetable[&Day::Friday].len(), 1);
+    assert!(merged.conflicts.is_empty());
+}
+
+#[test]
+fn reports_overlapping_classes_of_different_groups() {
+    let (a, b) = (group("A"), group("B"));
+    let merged = merge(vec![
+        (
+            a.clone(),
+            timetable(vec![event(&a, "Физика", Day::Tuesday, (9, 0), (10, 20))]),
+        ),
+        (
+            b.clone(),
+            timetable(vec![event(&b, "Химия", Day::Tuesday, (10, 0), (11, 20))]),
+        ),
+    ]);
+
+    let conflicts = &merged.conflicts[&Day::Tuesday];
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(
+        conflicts[0].start_time,
+        NaiveTime::from_hms_opt(10, 0, 0).unwrap()
+    );
+    assert_eq!(
+        conflicts[0].end_time,
+        NaiveTime::from_hms_opt(10, 20, 0).unwrap()
+    );
+    assert_eq!(conflicts[0].events[0].source, "A");
+    assert_eq!(conflicts[0].events[1].source, "B");
+}
+
+#[test]
+fn shared_classes_and_adjacent_ones_are_not_conflicts() {
+    let (a, b) = (group("A"), group("B"));
+    let merged = merge(vec![
+        (
+            a.clone(),
+            timetable(vec![
+                event(&a, "Физика", Day::Monday, (9, 0), (10, 20)),
+                // Subgroups of the same group may have classes at the same time
+                event(&a, "Химия", Day::Monday, (9, 0), (10, 20)),
+            ]),
+        ),
+        (
+            b.clone(),
+            timetable(vec![
+                event(&b, "Физика", Day::Monday, (9, 0), (10, 20)),
+                event(&b, "История", Day::Monday, (10, 20), (11, 40)),
+            ]),
+        ),
+    ]);
+
+    assert_eq!(merged.timetable[&Day::Monday].len(), 4);
+    let conflicts = &merged.conflicts[&Day::Monday];
+    // Only the chemistry class of A overlaps the shared physics lecture of B
+    assert_eq!(conflicts.len(), 1);
+    let names = conflicts[0]
+        .events
+        .iter()
+        .map(|event| event.event.name.as_str())
+        .collect::<Vec<_>>();
+    assert!(names.contains(&"Химия") && names.contains(&"Физика"));
+}
@@ -1,159 +1,1255 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
+use std::collections::HashMap;
 
-use actix_web::{get, web, HttpResponse, Responder};
-use serde::Serialize;
+use actix_web::{
+    delete, get, http::header, post, web, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
+    api::{links, url, ApiError, ApiResult, Envelope, ErrorBody, Linked, Links},
+    availability::{self, FreeSlot},
+    caching::{Cached, LIST_MAX_AGE, TIMETABLE_MAX_AGE},
+    calendar::{AcademicCalendar, CalendarDay},
     database::{
-        models::{Faculty, Uuid},
+        models::{
+            week_start_for, week_start_of, Bundle, Day, Event, Faculty, Group, Instructor,
+            NewWebhook, Resource, Room, SchemaVersion, SharedClass, TimetableChange, Uuid, Webhook,
+            WebhookDelivery,
+        },
         *,
     },
-    scraping,
+    export,
+    freshness::{self, Refreshes, Ttls},
+    ical,
+    lessons::{self, DayLessons, Upcoming},
+    merging::{self, MergedTimetable},
+    scraping::{self, RudnClient},
+    search,
 };
 
+/// Runs a database call on the blocking thread pool, so async workers are not stalled by it
+pub async fn run_db<T, F>(db: &Database, call: F) -> DBResult<T>
+where
+    F: FnOnce(&Database) -> DBResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let db = db.clone();
+    web::block(move || call(&db)).await.unwrap_or_else(|e| {
+        log::error!("Error: '{e}' while running a database call");
+        Err(DBError::Unavailable(String::from(
+            "Database is unavailable",
+        )))
+    })
+}
+
+/// Scrapes the resource in the background if its stored data is stale.
+/// Returns whether it is stale
+fn refresh_if_stale(
+    db: &web::Data<Database>,
+    client: &web::Data<dyn RudnClient>,
+    refreshes: &web::Data<Refreshes>,
+    ttls: &Ttls,
+    resource: Resource,
+    scraped_at: Option<NaiveDateTime>,
+) -> bool {
+    let stale = ttls.is_stale(resource, scraped_at, chrono::Utc::now().naive_utc());
+    if stale {
+        freshness::refresh_in_background(db.clone(), client.clone(), refreshes.clone(), resource);
+    }
+    stale
+}
+
+fn group_links(group_uuid: &str) -> Links {
+    links([
+        ("timetable", url(format!("/groups/{group_uuid}/timetable"))),
+        (
+            "calendar",
+            url(format!("/groups/{group_uuid}/timetable.ics")),
+        ),
+        ("changes", url(format!("/groups/{group_uuid}/changes"))),
+        ("upcoming", url(format!("/groups/{group_uuid}/upcoming"))),
+    ])
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiIndex {
+    pub version: &'static str,
+}
+
+/// This route lists the entry points of the API
+#[utoipa::path(
+    responses((status = 200, description = "Entry points of the API", body = Envelope<ApiIndex>)),
+    tag = "index"
+)]
 #[get("/")]
-pub async fn get_index() -> impl Responder {
-    HttpResponse::Ok().json(HashMap::from([("links", "/faculties")]))
+pub async fn get_index() -> Envelope<ApiIndex> {
+    Envelope::new(ApiIndex { version: "v1" }).with_links(links([
+        ("faculties", url("/faculties")),
+        ("instructors", url("/instructors")),
+        ("openapi", url("/openapi.json")),
+        ("status", url("/status")),
+    ]))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Status {
+    /// Version of the backend
+    pub version: &'static str,
+    pub schema: SchemaVersion,
+}
+
+/// This route reports versions of the backend and of its database schema,
+/// pending migrations are listed when the backend was started with `--no-migrate`
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Versions of the backend", body = Envelope<Status>),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "index"
+)]
+#[get("/status")]
+pub async fn get_status(db: web::Data<Database>) -> ApiResult<Envelope<Status>> {
+    let schema = run_db(&db, |db| db.get_schema_version()).await?;
+    Ok(Envelope::new(Status {
+        version: env!("CARGO_PKG_VERSION"),
+        schema,
+    }))
 }
 
 /// This route returns all faculties of the RUDN University from the database,
 /// if there is no faculties stored it scrapes info from the web and returns that.
-/// However, if at least one faculty is left in the database this function will not scrape the rest
+/// Stored faculties scraped longer than `FACULTIES_TTL` ago are returned marked as stale
+/// and scraped again in the background
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Every faculty", body = Envelope<Vec<Linked<Faculty>>>),
+        (status = 304, description = "Client's copy is up to date"),
+        (status = 502, description = "RUDN website could not be scraped", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "faculties"
+)]
 #[get("/faculties")]
-pub async fn get_faculties(db: web::Data<Arc<Mutex<Database>>>) -> impl Responder {
-    let faculties = {
-        let mut db = db.lock().unwrap();
-        db.get_faculties()
-    }; // drop MutexGuard
-
-    #[derive(Serialize)]
-    struct PositiveResponse<'a> {
+pub async fn get_faculties(
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    ttls: web::Data<Ttls>,
+    refreshes: web::Data<Refreshes>,
+) -> ApiResult<Cached<Envelope<Vec<Linked<Faculty>>>>> {
+    fn envelope(
         faculties: Vec<Faculty>,
-        links: HashMap<&'a str, &'a str>,
-    }
-
-    impl<'a> PositiveResponse<'a> {
-        fn new(faculties: Vec<Faculty>) -> Self {
-            Self {
-                faculties,
-                links: HashMap::from([("groups", "/{faculty_uuid}/groups")]),
-            }
-        }
+        last_modified: Option<NaiveDateTime>,
+        stale: bool,
+    ) -> Cached<Envelope<Vec<Linked<Faculty>>>> {
+        let faculties = faculties
+            .into_iter()
+            .map(|faculty| {
+                let groups = url(format!("/faculties/{}/groups", faculty.uuid));
+                Linked::new(faculty, links([("groups", groups)]))
+            })
+            .collect();
+        let envelope = Envelope::new(faculties)
+            .with_links(links([("search", url("/groups/search"))]))
+            .with_stale(stale);
+        Cached::new(envelope, last_modified, LIST_MAX_AGE)
     }
 
-    if let Ok(faculties) = faculties {
-        if !faculties.is_empty() {
+    let stored = run_db(&db, |db| {
+        Ok((
+            db.get_faculties()?,
+            db.get_last_modified(Resource::Faculties)?,
+            db.get_scraped_at(Resource::Faculties)?,
+        ))
+    })
+    .await;
+    match stored {
+        Ok((faculties, last_modified, scraped_at)) if !faculties.is_empty() => {
             log::debug!("Returning faculties data from the database");
-            return HttpResponse::Ok().json(PositiveResponse::new(faculties));
+            let stale = refresh_if_stale(
+                &db,
+                &client,
+                &refreshes,
+                &ttls,
+                Resource::Faculties,
+                scraped_at,
+            );
+            return Ok(envelope(faculties, last_modified, stale));
         }
+        Err(e @ DBError::Unavailable(_)) => return Err(e.into()),
+        _ => {}
     }
     // If the database is empty, scrape the data
-    if let Some(faculties) = scraping::scrape_faculties().await {
-        let mut db = db.lock().unwrap();
-        if db.update_faculties(&faculties).is_ok() {
-            log::debug!("Returning scraped faculties data");
-            return HttpResponse::Ok().json(PositiveResponse::new(faculties));
-        }
-    }
-
-    let msg = "Could not scrape faculties data from RUDN schedule webpage";
-    log::warn!("{msg}");
-
-    #[derive(Serialize)]
-    struct NegativeResponse<'a> {
-        reason: &'a str,
-    }
-
-    HttpResponse::NotFound().json(NegativeResponse { reason: msg })
+    let faculties = scraping::scrape_faculties(client.get_ref()).await?;
+    let (faculties, last_modified) = run_db(&db, |db| {
+        db.update_faculties(&faculties)?;
+        Ok((faculties, db.get_last_modified(Resource::Faculties)?))
+    })
+    .await?;
+    log::debug!("Returning scraped faculties data");
+    Ok(envelope(faculties, last_modified, false))
 }
 
-/// This route returns all student groups for given faculty
-#[get("/{faculty_uuid}/groups")]
+/// This route returns all student groups for given faculty.
+/// Stored groups scraped longer than `GROUPS_TTL` ago are returned marked as stale
+/// and scraped again in the background
+#[utoipa::path(
+    params(("faculty_uuid" = String, Path, description = "UUID of the faculty")),
+    responses(
+        (status = 200, description = "Groups of the faculty", body = Envelope<Vec<Linked<Group>>>),
+        (status = 304, description = "Client's copy is up to date"),
+        (status = 404, description = "Faculty has no groups", body = ErrorBody),
+        (status = 502, description = "RUDN website could not be scraped", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/faculties/{faculty_uuid}/groups")]
 pub async fn get_groups(
     faculty_uuid: web::Path<Uuid>,
-    db: web::Data<Arc<Mutex<Database>>>,
-) -> impl Responder {
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    ttls: web::Data<Ttls>,
+    refreshes: web::Data<Refreshes>,
+) -> ApiResult<Cached<Envelope<Vec<Linked<Group>>>>> {
+    fn envelope(
+        groups: Vec<Group>,
+        last_modified: Option<NaiveDateTime>,
+        stale: bool,
+    ) -> Cached<Envelope<Vec<Linked<Group>>>> {
+        let groups = groups
+            .into_iter()
+            .map(|group| {
+                let links = group_links(&group.uuid);
+                Linked::new(group, links)
+            })
+            .collect();
+        let envelope = Envelope::new(groups)
+            .with_links(links([("faculties", url("/faculties"))]))
+            .with_stale(stale);
+        Cached::new(envelope, last_modified, LIST_MAX_AGE)
+    }
+
     let groups = {
-        let mut db = db.lock().unwrap();
-        db.get_groups_for_faculty(&faculty_uuid)
+        let faculty_uuid = faculty_uuid.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_groups_for_faculty(&faculty_uuid)?,
+                db.get_last_modified(Resource::Groups(&faculty_uuid))?,
+                db.get_scraped_at(Resource::Groups(&faculty_uuid))?,
+            ))
+        })
+        .await
     };
 
-    if let Ok(groups) = groups {
-        if !groups.is_empty() {
+    match groups {
+        Ok((groups, last_modified, scraped_at)) if !groups.is_empty() => {
             log::debug!("Returning groups data from the database");
-            return HttpResponse::Ok().json(groups);
+            let resource = Resource::Groups(&faculty_uuid);
+            let stale = refresh_if_stale(&db, &client, &refreshes, &ttls, resource, scraped_at);
+            let groups = groups.into_values().flatten().collect();
+            return Ok(envelope(groups, last_modified, stale));
         }
+        Err(e @ DBError::Unavailable(_)) => return Err(e.into()),
+        _ => {}
     }
     // We do not have group data in DB, scrape anew
-    if let Some(scraped_groups) = scraping::scrape_group(&faculty_uuid).await {
-        let mut db = db.lock().unwrap();
-        if db.update_groups(&scraped_groups).is_ok() {
-            log::debug!("Returning scraped groups data");
-            return HttpResponse::Ok().json(scraped_groups);
-        }
+    let scraped_groups = scraping::scrape_group(client.get_ref(), &faculty_uuid).await?;
+    let (scraped_groups, last_modified) = {
+        let faculty_uuid = faculty_uuid.clone();
+        run_db(&db, move |db| {
+            db.update_groups(&scraped_groups)?;
+            let last_modified = db.get_last_modified(Resource::Groups(&faculty_uuid))?;
+            Ok((scraped_groups, last_modified))
+        })
+        .await?
+    };
+    log::debug!("Returning scraped groups data");
+    Ok(envelope(scraped_groups, last_modified, false))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SearchQuery {
+    /// Group name or its part, in Cyrillic or transliterated to Latin
+    q: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GroupMatch {
+    #[serde(flatten)]
+    pub group: Group,
+    /// Absent when the faculty of the group is not stored
+    pub faculty_name: Option<String>,
+}
+
+/// This route searches stored groups of every faculty by name.
+/// Matching ignores case, separators and the alphabet the name is typed in,
+/// and tolerates a few typos in longer queries
+#[utoipa::path(
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Best matches first", body = Envelope<Vec<Linked<GroupMatch>>>),
+        (status = 400, description = "Query has no letters or digits", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/search")]
+pub async fn search_groups(
+    query: web::Query<SearchQuery>,
+    db: web::Data<Database>,
+) -> ApiResult<Envelope<Vec<Linked<GroupMatch>>>> {
+    if search::normalize(&query.q).is_empty() {
+        return Err(ApiError::BadRequest(String::from(
+            "Query must contain a letter or a digit",
+        )));
     }
 
-    let msg = format!("Could not scrape group data for this faculty: {faculty_uuid}");
-    log::warn!("{msg}");
+    let groups = run_db(&db, |db| db.get_groups_with_faculties()).await?;
+    let matches = search::find_groups(&query.q, groups)
+        .into_iter()
+        .map(|(group, faculty)| {
+            let links = group_links(&group.uuid);
+            let group_match = GroupMatch {
+                faculty_name: faculty.map(|faculty| faculty.name),
+                group,
+            };
+            Linked::new(group_match, links)
+        })
+        .collect();
+    Ok(Envelope::new(matches))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TimetableQuery {
+    /// ISO week number, the closest week with this number is selected
+    week: Option<u32>,
+    /// Any date within the requested week
+    date: Option<NaiveDate>,
+}
 
-    #[derive(Serialize)]
-    struct NegativeResponse<'a> {
-        reason: &'a str,
-        links: HashMap<&'a str, &'a str>,
+impl TimetableQuery {
+    fn week_start(&self) -> ApiResult<NaiveDate> {
+        requested_week_start(self.date, self.week)
     }
+}
 
-    HttpResponse::NotFound().json(NegativeResponse {
-        reason: &msg,
-        links: HashMap::from([("faculties", "/faculties")]),
-    })
+/// Returns the Monday of the week requested either by a date within it or by its number,
+/// current week is used by default
+fn requested_week_start(date: Option<NaiveDate>, week: Option<u32>) -> ApiResult<NaiveDate> {
+    let today = lessons::now().date_naive();
+    match (date, week) {
+        (Some(date), _) => Some(week_start_of(date)),
+        (None, Some(week)) => week_start_for(week, today),
+        (None, None) => Some(week_start_of(today)),
+    }
+    .ok_or_else(|| ApiError::BadRequest(String::from("Invalid week number")))
+}
+
+/// Links to the weeks around the one starting on `week_start` of a timetable at `path`
+fn week_links(path: &str, week_start: NaiveDate) -> Links {
+    let week_url = |week_start: NaiveDate| url(format!("{path}?date={week_start}"));
+    links([
+        ("previous_week", week_url(week_start - Duration::weeks(1))),
+        ("next_week", week_url(week_start + Duration::weeks(1))),
+    ])
 }
 
-/// This route returns current week timetable for specified group
-/// Accepts a query string with `group` as parameter;
-#[get("/{group_uuid}/timetable")]
+/// This route returns timetable of the specified group for one week.
+/// Accepts a query string with either `week` (ISO week number) or `date` (`YYYY-MM-DD`),
+/// current week is returned when neither is present.
+/// Classes on holidays and during the breaks between semesters are left out.
+/// Stored timetable scraped longer than `TIMETABLE_TTL` ago is returned marked as stale
+/// and scraped again in the background. A week without stored classes is scraped right away,
+/// unless the timetable was scraped within `TIMETABLE_TTL`
+#[utoipa::path(
+    params(
+        ("group_uuid" = String, Path, description = "UUID of the group"),
+        TimetableQuery,
+    ),
+    responses(
+        (status = 200, description = "Events of the week by day", body = Envelope<HashMap<Day, Vec<Event>>>),
+        (status = 304, description = "Client's copy is up to date"),
+        (status = 400, description = "Invalid week number", body = ErrorBody),
+        (status = 502, description = "RUDN website could not be scraped", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/{group_uuid}/timetable")]
 pub async fn get_timetable(
     group_uuid: web::Path<Uuid>,
-    db: web::Data<Arc<Mutex<Database>>>,
-) -> impl Responder {
+    query: web::Query<TimetableQuery>,
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    ttls: web::Data<Ttls>,
+    refreshes: web::Data<Refreshes>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Cached<Envelope<HashMap<Day, Vec<Event>>>>> {
+    let week_start = query.week_start()?;
+    let envelope = |timetable, stale| {
+        let timetable = academic_calendar.study_days_only(timetable);
+        let mut links = group_links(&group_uuid);
+        links.remove("timetable");
+        links.extend(week_links(
+            &format!("/groups/{group_uuid}/timetable"),
+            week_start,
+        ));
+        let envelope = Envelope::new(timetable).with_links(links).with_stale(stale);
+        // Validated by the ETag alone: the modification time of the group's timetable
+        // tells neither about the requested week nor about the academic calendar
+        Cached::new(envelope, None, TIMETABLE_MAX_AGE)
+    };
+
     let timetable = {
-        let mut db = db.lock().unwrap();
-        db.get_timetable_for_group(&group_uuid)
+        let group_uuid = group_uuid.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_timetable_for_group(&group_uuid, &week_start)?,
+                db.get_scraped_at(Resource::Timetable(&group_uuid))?,
+            ))
+        })
+        .await
     };
 
-    if let Ok(timetable) = timetable {
-        if !timetable.is_empty() {
+    let resource = Resource::Timetable(&group_uuid);
+    let now = chrono::Utc::now().naive_utc();
+    match timetable {
+        // A week without classes is returned as is, unless the timetable is due to be scraped
+        Ok((timetable, scraped_at))
+            if !timetable.is_empty() || !ttls.is_stale(resource, scraped_at, now) =>
+        {
             log::debug!("Returning timetable data from the database");
-            return HttpResponse::Ok().json(timetable);
+            let stale = refresh_if_stale(&db, &client, &refreshes, &ttls, resource, scraped_at);
+            return Ok(envelope(timetable, stale));
+        }
+        Err(e @ DBError::Unavailable(_)) => return Err(e.into()),
+        _ => {}
+    }
+
+    let scraped_timetable = scraping::scrape_timetable(client.get_ref(), &group_uuid).await?;
+    let scraped_timetable = run_db(&db, move |db| {
+        db.update_timetable(&scraped_timetable)?;
+        Ok(scraped_timetable)
+    })
+    .await?;
+    log::debug!("Returning scraped timetable data");
+    let week_timetable = scraped_timetable.week_by_day(week_start);
+    Ok(envelope(week_timetable, false))
+}
+
+/// Maximum number of groups accepted by the routes combining timetables of several groups
+const MAX_COMBINED_GROUPS: usize = 10;
+
+/// Parses comma separated group UUIDs, duplicates are dropped
+fn parse_group_list(groups: &str) -> ApiResult<Vec<Uuid>> {
+    let mut uuids = groups
+        .split(',')
+        .map(str::trim)
+        .filter(|uuid| !uuid.is_empty())
+        .map(String::from)
+        .collect::<Vec<Uuid>>();
+    uuids.sort_unstable();
+    uuids.dedup();
+    if uuids.is_empty() || uuids.len() > MAX_COMBINED_GROUPS {
+        return Err(ApiError::BadRequest(format!(
+            "From 1 to {MAX_COMBINED_GROUPS} comma separated group UUIDs are required"
+        )));
+    }
+    Ok(uuids)
+}
+
+/// Retrieves stored timetables of the groups for the week starting on `week_start`
+/// without the classes on the days off, fails listing the groups missing from the database
+async fn load_group_timetables(
+    db: &web::Data<Database>,
+    academic_calendar: &AcademicCalendar,
+    group_uuids: Vec<Uuid>,
+    week_start: NaiveDate,
+) -> ApiResult<Vec<(Group, HashMap<Day, Vec<Event>>)>> {
+    let (timetables, unknown) = run_db(db, move |db| {
+        let mut timetables = vec![];
+        let mut unknown = vec![];
+        for uuid in group_uuids {
+            match db.get_group(&uuid)? {
+                Some(group) => {
+                    let timetable = db.get_timetable_for_group(&uuid, &week_start)?;
+                    timetables.push((group, timetable));
+                }
+                None => unknown.push(uuid),
+            }
         }
+        Ok((timetables, unknown))
+    })
+    .await?;
+
+    if !unknown.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "Groups {} are not found",
+            unknown.join(", ")
+        )));
+    }
+    Ok(timetables
+        .into_iter()
+        .map(|(group, timetable)| (group, academic_calendar.study_days_only(timetable)))
+        .collect())
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct MergedTimetableQuery {
+    /// Comma separated UUIDs of the groups
+    groups: String,
+    /// ISO week number, the closest week with this number is selected
+    week: Option<u32>,
+    /// Any date within the requested week
+    date: Option<NaiveDate>,
+}
+
+/// This route returns timetables of several groups for one week combined into one,
+/// every event is tagged with the group it comes from and overlapping classes
+/// of different groups are reported as conflicts.
+/// Accepts `groups` with comma separated group UUIDs and the same week parameters
+/// as the group's timetable route. Only stored timetables are merged
+#[utoipa::path(
+    params(MergedTimetableQuery),
+    responses(
+        (status = 200, description = "Combined timetable with conflicts", body = Envelope<MergedTimetable>),
+        (status = 400, description = "Invalid week number or list of groups", body = ErrorBody),
+        (status = 404, description = "Some of the groups are not stored", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/timetable/merged")]
+pub async fn get_merged_timetable(
+    query: web::Query<MergedTimetableQuery>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<MergedTimetable>> {
+    let week_start = requested_week_start(query.date, query.week)?;
+    let group_uuids = parse_group_list(&query.groups)?;
+
+    let timetables =
+        load_group_timetables(&db, &academic_calendar, group_uuids, week_start).await?;
+    Ok(Envelope::new(merging::merge(timetables)))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct FreeSlotsQuery {
+    /// Comma separated UUIDs of the groups
+    groups: String,
+    /// Shortest window worth returning, one class long by default
+    min_minutes: Option<i64>,
+    /// ISO week number, the closest week with this number is selected
+    week: Option<u32>,
+    /// Any date within the requested week
+    date: Option<NaiveDate>,
+}
+
+/// This route returns windows of the week when none of the groups has classes.
+/// Windows are aligned to the class slots of 90 minutes starting at 09:00.
+/// Accepts `groups` with comma separated group UUIDs, optional `min_minutes`
+/// and the same week parameters as the group's timetable route
+#[utoipa::path(
+    params(FreeSlotsQuery),
+    responses(
+        (status = 200, description = "Free windows by day", body = Envelope<HashMap<Day, Vec<FreeSlot>>>),
+        (status = 400, description = "Invalid week number, list of groups or window length", body = ErrorBody),
+        (status = 404, description = "Some of the groups are not stored", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/free-slots")]
+pub async fn get_free_slots(
+    query: web::Query<FreeSlotsQuery>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<HashMap<Day, Vec<FreeSlot>>>> {
+    let week_start = requested_week_start(query.date, query.week)?;
+    let group_uuids = parse_group_list(&query.groups)?;
+    let min_minutes = query.min_minutes.unwrap_or(availability::CLASS_MINUTES);
+    if min_minutes <= 0 {
+        return Err(ApiError::BadRequest(String::from(
+            "Minimal window length must be positive",
+        )));
     }
 
-    if let Some(scraped_timetable) = scraping::scrape_timetable(&group_uuid).await {
-        let mut db = db.lock().unwrap();
-        if db.update_timetable(&scraped_timetable).is_ok() {
-            log::debug!("Returning scraped timetable data");
-            return HttpResponse::Ok().json(scraped_timetable);
+    let timetables = load_group_timetables(&db, &academic_calendar, group_uuids, week_start)
+        .await?
+        .into_iter()
+        .map(|(_, timetable)| timetable)
+        .collect::<Vec<_>>();
+    Ok(Envelope::new(availability::free_slots(
+        &timetables,
+        min_minutes,
+    )))
+}
+
+fn calendar_response(
+    calendar_name: &str,
+    events: &[Event],
+    academic_calendar: &AcademicCalendar,
+) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(ical::render_calendar(
+            calendar_name,
+            events,
+            academic_calendar,
+        ))
+}
+
+/// This route returns all stored weeks of the group's timetable as an iCalendar feed,
+/// so calendar applications can subscribe to it. Classes on the days off are left out
+#[utoipa::path(
+    params(("group_uuid" = String, Path, description = "UUID of the group")),
+    responses(
+        (status = 200, description = "iCalendar feed", content_type = "text/calendar", body = String),
+        (status = 502, description = "RUDN website could not be scraped", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/{group_uuid}/timetable.ics")]
+pub async fn get_timetable_ics(
+    group_uuid: web::Path<Uuid>,
+    db: web::Data<Database>,
+    client: web::Data<dyn RudnClient>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<HttpResponse> {
+    let stored = {
+        let group_uuid = group_uuid.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_events_for_group(&group_uuid)?,
+                db.get_group(&group_uuid)?,
+            ))
+        })
+        .await
+    };
+    let (events, group) = match stored {
+        Ok(stored) => stored,
+        Err(e @ DBError::Unavailable(_)) => return Err(e.into()),
+        Err(_) => (vec![], None),
+    };
+    let calendar_name = group.map_or_else(|| group_uuid.to_string(), |group| group.name);
+
+    let events = match events {
+        events if !events.is_empty() => events,
+        _ => {
+            let scraped_timetable =
+                scraping::scrape_timetable(client.get_ref(), &group_uuid).await?;
+            run_db(&db, |db| {
+                db.update_timetable(&scraped_timetable)
+                    .map(|_| scraped_timetable)
+            })
+            .await?
+            .into_events()
         }
+    };
+
+    Ok(calendar_response(
+        &calendar_name,
+        &events,
+        &academic_calendar,
+    ))
+}
+
+fn group_not_found(group_uuid: &str) -> ApiError {
+    ApiError::NotFound(format!("Group {group_uuid} is not found"))
+}
+
+/// This route returns lessons of the group on the date with their start and end
+/// in Moscow time, along with the academic calendar of the date.
+/// Only stored timetables are used, there are no lessons on the days off
+#[utoipa::path(
+    params(
+        ("group_uuid" = String, Path, description = "UUID of the group"),
+        ("date" = NaiveDate, Path, description = "Date in the `YYYY-MM-DD` format"),
+    ),
+    responses(
+        (status = 200, description = "Lessons of the date", body = Envelope<DayLessons>),
+        (status = 400, description = "Invalid date", body = ErrorBody),
+        (status = 404, description = "Group is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/{group_uuid}/day/{date}")]
+pub async fn get_day(
+    path: web::Path<(Uuid, NaiveDate)>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<DayLessons>> {
+    let (group_uuid, date) = path.into_inner();
+    let (group, timetable) = {
+        let group_uuid = group_uuid.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_group(&group_uuid)?,
+                db.get_timetable_for_group(&group_uuid, &week_start_of(date))?,
+            ))
+        })
+        .await?
+    };
+    if group.is_none() {
+        return Err(group_not_found(&group_uuid));
+    }
+
+    let events = timetable.into_values().flatten().collect();
+    let mut links = group_links(&group_uuid);
+    let day_url = |date: NaiveDate| url(format!("/groups/{group_uuid}/day/{date}"));
+    links.insert(
+        String::from("previous_day"),
+        day_url(date - Duration::days(1)),
+    );
+    links.insert(String::from("next_day"), day_url(date + Duration::days(1)));
+    Ok(Envelope::new(lessons::on_date(
+        date,
+        events,
+        &academic_calendar,
+        lessons::now(),
+    ))
+    .with_links(links))
+}
+
+/// Number of lessons returned by the upcoming lessons route by default and at most
+const DEFAULT_UPCOMING: usize = 5;
+const MAX_UPCOMING: usize = 50;
+
+#[derive(Deserialize, IntoParams)]
+pub struct UpcomingQuery {
+    /// Number of lessons, 5 by default and 50 at most
+    n: Option<usize>,
+}
+
+/// This route returns the next lessons of the group which have not ended yet,
+/// with the minutes left until each of them and until the next one starts, e.g. for widgets.
+/// Only stored timetables are used, lessons on the days off are left out
+#[utoipa::path(
+    params(
+        ("group_uuid" = String, Path, description = "UUID of the group"),
+        UpcomingQuery,
+    ),
+    responses(
+        (status = 200, description = "Upcoming lessons in the order they start", body = Envelope<Upcoming>),
+        (status = 400, description = "Invalid number of lessons", body = ErrorBody),
+        (status = 404, description = "Group is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/{group_uuid}/upcoming")]
+pub async fn get_upcoming(
+    group_uuid: web::Path<Uuid>,
+    query: web::Query<UpcomingQuery>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<Upcoming>> {
+    let count = query.n.unwrap_or(DEFAULT_UPCOMING);
+    if !(1..=MAX_UPCOMING).contains(&count) {
+        return Err(ApiError::BadRequest(format!(
+            "Number of lessons must be from 1 to {MAX_UPCOMING}"
+        )));
+    }
+
+    let (group, events) = {
+        let group_uuid = group_uuid.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_group(&group_uuid)?,
+                db.get_events_for_group(&group_uuid)?,
+            ))
+        })
+        .await?
+    };
+    if group.is_none() {
+        return Err(group_not_found(&group_uuid));
+    }
+
+    let upcoming = lessons::upcoming(events, &academic_calendar, lessons::now(), count);
+    let mut links = group_links(&group_uuid);
+    links.remove("upcoming");
+    Ok(Envelope::new(upcoming).with_links(links))
+}
+
+fn by_day(classes: Vec<SharedClass>) -> HashMap<Day, Vec<SharedClass>> {
+    let mut timetable: HashMap<Day, Vec<SharedClass>> = HashMap::new();
+    for class in classes {
+        timetable.entry(class.event.day).or_default().push(class);
+    }
+    timetable
+}
+
+fn instructor_not_found(instructor_id: i32) -> ApiError {
+    ApiError::NotFound(format!("Instructor {instructor_id} is not found"))
+}
+
+/// This route returns every instructor found in the stored timetables
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Instructors ordered by name", body = Envelope<Vec<Linked<Instructor>>>),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "instructors"
+)]
+#[get("/instructors")]
+pub async fn get_instructors(
+    db: web::Data<Database>,
+) -> ApiResult<Envelope<Vec<Linked<Instructor>>>> {
+    let instructors = run_db(&db, |db| db.get_instructors())
+        .await?
+        .into_iter()
+        .map(|instructor| {
+            let links = links([
+                (
+                    "timetable",
+                    url(format!("/instructors/{}/timetable", instructor.id)),
+                ),
+                (
+                    "calendar",
+                    url(format!("/instructors/{}/timetable.ics", instructor.id)),
+                ),
+            ]);
+            Linked::new(instructor, links)
+        })
+        .collect();
+    Ok(Envelope::new(instructors))
+}
+
+/// This route returns classes of the instructor across every group for one week.
+/// Accepts the same query string as the group's timetable route.
+/// Classes attended by several groups at once are listed once with every group
+#[utoipa::path(
+    params(
+        ("instructor_id" = i32, Path, description = "Id of the instructor"),
+        TimetableQuery,
+    ),
+    responses(
+        (status = 200, description = "Classes of the week by day", body = Envelope<HashMap<Day, Vec<SharedClass>>>),
+        (status = 400, description = "Invalid week number", body = ErrorBody),
+        (status = 404, description = "Instructor is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "instructors"
+)]
+#[get("/instructors/{instructor_id}/timetable")]
+pub async fn get_instructor_timetable(
+    instructor_id: web::Path<i32>,
+    query: web::Query<TimetableQuery>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<HashMap<Day, Vec<SharedClass>>>> {
+    let week_start = query.week_start()?;
+
+    let instructor_id = instructor_id.into_inner();
+    let (instructor, mut events) = run_db(&db, move |db| {
+        Ok((
+            db.get_instructor(instructor_id)?,
+            db.get_events_for_instructor(instructor_id, Some(&week_start))?,
+        ))
+    })
+    .await?;
+    if instructor.is_none() {
+        return Err(instructor_not_found(instructor_id));
+    }
+    events.retain(|event| academic_calendar.takes_place(event));
+
+    let mut links = week_links(
+        &format!("/instructors/{instructor_id}/timetable"),
+        week_start,
+    );
+    links.insert(
+        String::from("calendar"),
+        url(format!("/instructors/{instructor_id}/timetable.ics")),
+    );
+    Ok(Envelope::new(by_day(SharedClass::merge(events))).with_links(links))
+}
+
+/// This route returns every stored week of the instructor's classes as an iCalendar feed
+#[utoipa::path(
+    params(("instructor_id" = i32, Path, description = "Id of the instructor")),
+    responses(
+        (status = 200, description = "iCalendar feed", content_type = "text/calendar", body = String),
+        (status = 404, description = "Instructor is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "instructors"
+)]
+#[get("/instructors/{instructor_id}/timetable.ics")]
+pub async fn get_instructor_timetable_ics(
+    instructor_id: web::Path<i32>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<HttpResponse> {
+    let instructor_id = instructor_id.into_inner();
+    let (instructor, events) = run_db(&db, move |db| {
+        Ok((
+            db.get_instructor(instructor_id)?,
+            db.get_events_for_instructor(instructor_id, None)?,
+        ))
+    })
+    .await?;
+    let instructor = instructor.ok_or_else(|| instructor_not_found(instructor_id))?;
+
+    let events = SharedClass::merge(events)
+        .into_iter()
+        .map(|class| class.event)
+        .collect::<Vec<_>>();
+    Ok(calendar_response(
+        &instructor.name,
+        &events,
+        &academic_calendar,
+    ))
+}
+
+/// Path of the room's timetable, room names may contain spaces and slashes
+fn room_timetable_path(room: &str) -> String {
+    format!(
+        "/rooms/{}/timetable",
+        utf8_percent_encode(room, NON_ALPHANUMERIC)
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct FreeRoomsQuery {
+    /// Day of the week, e.g. `Tuesday`, required unless `date` is present
+    #[param(inline)]
+    day: Option<Day>,
+    /// ISO week number, the closest week with this number is selected
+    week: Option<u32>,
+    /// Date to look for free rooms on, sets both the day and the week
+    date: Option<NaiveDate>,
+    /// Beginning of the time span the room is needed for, e.g. `12:00`
+    from: NaiveTime,
+    /// End of the time span the room is needed for, e.g. `13:30`
+    to: NaiveTime,
+    /// Only rooms of this building are returned
+    building: Option<String>,
+}
+
+/// This route returns rooms which have no classes of any group during the time span.
+/// Only rooms appearing in the stored timetables are known,
+/// so a room is considered free when no stored class takes place in it
+#[utoipa::path(
+    params(FreeRoomsQuery),
+    responses(
+        (status = 200, description = "Free rooms ordered by name", body = Envelope<Vec<Linked<Room>>>),
+        (status = 400, description = "Invalid day, week or time span", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "rooms"
+)]
+#[get("/rooms/free")]
+pub async fn get_free_rooms(
+    query: web::Query<FreeRoomsQuery>,
+    db: web::Data<Database>,
+) -> ApiResult<Envelope<Vec<Linked<Room>>>> {
+    let query = query.into_inner();
+    let week_start = requested_week_start(query.date, query.week)?;
+    let day = query.date.map_or(query.day, Day::of).ok_or_else(|| {
+        ApiError::BadRequest(String::from(
+            "Either a day of the week or a date except Sunday is required",
+        ))
+    })?;
+    if query.from >= query.to {
+        return Err(ApiError::BadRequest(String::from(
+            "Time span must end after it starts",
+        )));
+    }
+
+    let rooms = run_db(&db, move |db| {
+        db.get_free_rooms(&week_start, day, query.from, query.to)
+    })
+    .await?;
+    let building = query.building.map(|building| building.to_lowercase());
+    let rooms = rooms
+        .into_iter()
+        .filter(|room| {
+            building.is_none() || room.building.as_ref().map(|b| b.to_lowercase()) == building
+        })
+        .map(|room| {
+            let links = links([("timetable", url(room_timetable_path(&room.name)))]);
+            Linked::new(room, links)
+        })
+        .collect();
+    Ok(Envelope::new(rooms))
+}
+
+/// This route returns classes of every group taking place in the room for one week.
+/// Accepts the same query string as the group's timetable route
+#[utoipa::path(
+    params(
+        ("room" = String, Path, description = "Name of the room as written in the timetable"),
+        TimetableQuery,
+    ),
+    responses(
+        (status = 200, description = "Classes of the week by day", body = Envelope<HashMap<Day, Vec<SharedClass>>>),
+        (status = 400, description = "Invalid week number", body = ErrorBody),
+        (status = 404, description = "Room is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "rooms"
+)]
+#[get("/rooms/{room}/timetable")]
+pub async fn get_room_timetable(
+    room: web::Path<String>,
+    query: web::Query<TimetableQuery>,
+    db: web::Data<Database>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> ApiResult<Envelope<HashMap<Day, Vec<SharedClass>>>> {
+    let week_start = query.week_start()?;
+
+    let room_name = room.into_inner();
+    let (room, mut events) = {
+        let room_name = room_name.clone();
+        run_db(&db, move |db| {
+            Ok((
+                db.get_room(&room_name)?,
+                db.get_events_in_room(&room_name, &week_start)?,
+            ))
+        })
+        .await?
+    };
+    if room.is_none() {
+        return Err(ApiError::NotFound(format!("Room {room_name} is not found")));
+    }
+    events.retain(|event| academic_calendar.takes_place(event));
+
+    let links = week_links(&room_timetable_path(&room_name), week_start);
+    Ok(Envelope::new(by_day(SharedClass::merge(events))).with_links(links))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CalendarQuery {
+    /// Date to describe, today by default
+    date: Option<NaiveDate>,
+}
+
+/// This route returns the semester and the academic week of the date
+/// and whether classes take place on it
+#[utoipa::path(
+    params(CalendarQuery),
+    responses(
+        (status = 200, description = "Academic calendar of the date", body = Envelope<CalendarDay>),
+        (status = 400, description = "Invalid date", body = ErrorBody),
+    ),
+    tag = "calendar"
+)]
+#[get("/calendar")]
+pub async fn get_calendar(
+    query: web::Query<CalendarQuery>,
+    academic_calendar: web::Data<AcademicCalendar>,
+) -> Envelope<CalendarDay> {
+    let date = query.date.unwrap_or_else(|| lessons::now().date_naive());
+    Envelope::new(academic_calendar.day(date))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ChangesQuery {
+    /// Only changes made after this moment (UTC) are returned
+    since: Option<NaiveDateTime>,
+}
+
+/// This route returns recorded changes of the group's timetable.
+/// Accepts a query string with optional `since` parameter (`YYYY-MM-DDTHH:MM:SS`, UTC)
+#[utoipa::path(
+    params(
+        ("group_uuid" = String, Path, description = "UUID of the group"),
+        ChangesQuery,
+    ),
+    responses(
+        (status = 200, description = "Changes in the order they were recorded", body = Envelope<Vec<TimetableChange>>),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    tag = "groups"
+)]
+#[get("/groups/{group_uuid}/changes")]
+pub async fn get_changes(
+    group_uuid: web::Path<Uuid>,
+    query: web::Query<ChangesQuery>,
+    db: web::Data<Database>,
+) -> ApiResult<Envelope<Vec<TimetableChange>>> {
+    let since = query.since;
+    let links = links([("timetable", url(format!("/groups/{group_uuid}/timetable")))]);
+    let changes = run_db(&db, move |db| db.get_changes_for_group(&group_uuid, since)).await?;
+    Ok(Envelope::new(changes).with_links(links))
+}
+
+/// Checks the `Auth-Token` header against the `ADMIN_TOKEN` environment variable,
+/// administrative routes are disabled when the variable is not set or empty.
+/// HMACs of both tokens are compared in constant time, so the response time
+/// does not tell how much of the token was guessed
+fn require_admin(req: &HttpRequest) -> ApiResult<()> {
+    let admin_token = std::env::var("ADMIN_TOKEN").unwrap_or_default();
+    if admin_token.is_empty() {
+        return Err(ApiError::Unauthorized);
     }
+    let mac = |token: &[u8]| {
+        let mut mac = Hmac::<Sha256>::new_from_slice(admin_token.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(token);
+        mac
+    };
+    let expected = mac(admin_token.as_bytes()).finalize().into_bytes();
+    let is_admin = req
+        .headers()
+        .get("Auth-Token")
+        .is_some_and(|token| mac(token.as_bytes()).verify_slice(&expected).is_ok());
+    if !is_admin {
+        return Err(ApiError::Unauthorized);
+    }
+    Ok(())
+}
 
-    let msg = format!("Could not scrape timetable data for this group: {group_uuid}");
-    log::warn!("{msg}");
+/// This route subscribes an URL to timetable changes of a group or of every group.
+/// Payloads are signed with the provided secret, see [`crate::webhooks::sign`]
+#[utoipa::path(
+    request_body = NewWebhook,
+    responses(
+        (status = 201, description = "Webhook is subscribed", body = Envelope<Webhook>),
+        (status = 401, description = "Valid admin token is required", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+    tag = "webhooks"
+)]
+#[post("/webhooks")]
+pub async fn add_webhook(
+    req: HttpRequest,
+    webhook: web::Json<NewWebhook>,
+    db: web::Data<Database>,
+) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
 
-    #[derive(Serialize)]
-    struct Response<'a> {
-        reason: &'a str,
-        links: HashMap<&'static str, &'static str>,
+    let webhook = run_db(&db, move |db| db.add_webhook(&webhook)).await?;
+    let links = links([("self", url(format!("/webhooks/{}", webhook.id)))]);
+    Ok(HttpResponse::Created().json(Envelope::new(webhook).with_links(links)))
+}
+
+/// This route unsubscribes a webhook, its pending deliveries are dropped
+#[utoipa::path(
+    params(("id" = i32, Path, description = "Id of the webhook")),
+    responses(
+        (status = 204, description = "Webhook is unsubscribed"),
+        (status = 401, description = "Valid admin token is required", body = ErrorBody),
+        (status = 404, description = "Webhook is not found", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+    tag = "webhooks"
+)]
+#[delete("/webhooks/{id}")]
+pub async fn delete_webhook(
+    req: HttpRequest,
+    id: web::Path<i32>,
+    db: web::Data<Database>,
+) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+
+    let id = id.into_inner();
+    if run_db(&db, move |db| db.delete_webhook(id)).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApiError::NotFound(format!("Webhook {id} is not found")))
     }
+}
+
+/// This route returns deliveries which failed too many times and will not be retried
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Dead deliveries, the most recent first", body = Envelope<Vec<WebhookDelivery>>),
+        (status = 401, description = "Valid admin token is required", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+    tag = "webhooks"
+)]
+#[get("/webhooks/dead-letters")]
+pub async fn get_dead_letters(
+    req: HttpRequest,
+    db: web::Data<Database>,
+) -> ApiResult<Envelope<Vec<WebhookDelivery>>> {
+    require_admin(&req)?;
 
-    let body = Response {
-        reason: &msg,
-        links: HashMap::from([
-            ("faculties", "/faculties"),
-            ("groups", "/{faculty_uuid}/groups"),
-        ]),
+    let deliveries = run_db(&db, |db| db.get_dead_deliveries()).await?;
+    Ok(Envelope::new(deliveries))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ExportQuery {
+    /// JSON bundle of every table by default
+    #[param(inline)]
+    format: Option<export::Format>,
+    /// Table to export as CSV, required by the CSV format
+    #[param(inline)]
+    table: Option<export::Table>,
+}
+
+/// This route returns the stored faculties, groups and timetables as a bundle,
+/// which can be imported with `backend db import`, or a single table as CSV.
+/// The bundle is returned as it is instead of in the envelope, so it can be saved to a file
+#[utoipa::path(
+    params(ExportQuery),
+    responses(
+        (status = 200, description = "Bundle of every table or a table with a header row", content(
+            (Bundle = "application/json"),
+            (String = "text/csv"),
+        )),
+        (status = 400, description = "Table is missing for the CSV format", body = ErrorBody),
+        (status = 401, description = "Valid admin token is required", body = ErrorBody),
+        (status = 503, description = "Database is unavailable", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+    tag = "admin"
+)]
+#[get("/export")]
+pub async fn get_export(
+    req: HttpRequest,
+    query: web::Query<ExportQuery>,
+    db: web::Data<Database>,
+) -> ApiResult<HttpResponse> {
+    require_admin(&req)?;
+
+    let format = query.format.unwrap_or_default();
+    let table = match (format, query.table) {
+        (export::Format::Csv, None) => {
+            return Err(ApiError::BadRequest(String::from(
+                "Table is required for the CSV format",
+            )))
+        }
+        (_, table) => table,
     };
 
-    HttpResponse::NotFound().json(body)
+    let bundle = run_db(&db, |db| db.export()).await?;
+    Ok(match (format, table) {
+        (export::Format::Csv, Some(table)) => {
+            let mut body = vec![];
+            // Rows are written to memory and every field is serializable
+            export::write_csv(&bundle, table, &mut body).expect("CSV is written to memory");
+            HttpResponse::Ok()
+                .content_type("text/csv; charset=utf-8")
+                .insert_header((
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", table.file_name()),
+                ))
+                .body(body)
+        }
+        _ => HttpResponse::Ok()
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"export.json\"",
+            ))
+            .json(bundle),
+    })
+}
+
+/// Responds to requests of unknown routes in the shape of the other errors
+pub async fn not_found(req: HttpRequest) -> impl Responder {
+    ApiError::NotFound(format!("Route {} is not found", req.path())).error_response()
 }
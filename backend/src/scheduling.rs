@@ -1,23 +1,157 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use delay_timer::prelude::*;
 
-use crate::database::Database;
+use crate::{
+    database::Database,
+    routes::run_db,
+    scraping::{self, RudnClient},
+    webhooks,
+};
+
+/// Statistics of a single bulk scraping run
+#[derive(Debug, Default)]
+pub struct ScrapeStats {
+    /// Number of entities successfully scraped and stored
+    pub scraped: usize,
+    /// Number of entities which could not be scraped or stored
+    pub failed: usize,
+    /// Number of scraped entities which differ from the stored ones
+    pub changed: usize,
+    pub duration: Duration,
+}
+
+impl Display for ScrapeStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} scraped, {} changed, {} failed in {:.2?}",
+            self.scraped, self.changed, self.failed, self.duration
+        )
+    }
+}
+
+/// Scrapes all faculties of the university and stores them in the database
+pub async fn scrape_all_faculties(db: Database, client: &dyn RudnClient) -> ScrapeStats {
+    let start = Instant::now();
+    let mut stats = ScrapeStats::default();
+
+    match scraping::scrape_faculties(client).await {
+        Ok(faculties) => {
+            let count = faculties.len();
+            match run_db(&db, move |db| db.update_faculties(&faculties)).await {
+                Ok(_) => stats.scraped += count,
+                Err(e) => {
+                    log::error!("Could not store scraped faculties: {e}");
+                    stats.failed += count;
+                }
+            }
+        }
+        Err(e) => {
+            log::warn!("Could not scrape faculties: {e}");
+            stats.failed += 1;
+        }
+    }
+
+    stats.duration = start.elapsed();
+    stats
+}
+
+/// Scrapes student groups of every faculty stored in the database.
+/// If there are no faculties in the database they are scraped first
+pub async fn scrape_all_groups(db: Database, client: &dyn RudnClient) -> ScrapeStats {
+    let start = Instant::now();
+    let mut stats = ScrapeStats::default();
+
+    let get_faculties = || run_db(&db, |db| db.get_faculties());
+    let mut faculties = get_faculties().await.unwrap_or_default();
+    if faculties.is_empty() {
+        log::info!("No faculties in the database, scraping them first");
+        scrape_all_faculties(db.clone(), client).await;
+        faculties = get_faculties().await.unwrap_or_default();
+    }
+
+    for faculty in faculties {
+        match scraping::scrape_group(client, &faculty.uuid).await {
+            Ok(groups) => {
+                let count = groups.len();
+                match run_db(&db, move |db| db.update_groups(&groups)).await {
+                    Ok(_) => stats.scraped += count,
+                    Err(e) => {
+                        log::error!("Could not store groups of faculty {}: {e}", faculty.uuid);
+                        stats.failed += 1;
+                    }
+                }
+            }
+            Err(e) => {
+                log::warn!("Could not scrape groups of faculty {}: {e}", faculty.uuid);
+                stats.failed += 1;
+            }
+        }
+    }
+
+    stats.duration = start.elapsed();
+    stats
+}
+
+/// Scrapes timetables of every student group stored in the database.
+/// If there are no groups in the database they are scraped first
+pub async fn scrape_all_timetables(db: Database, client: &dyn RudnClient) -> ScrapeStats {
+    let start = Instant::now();
+    let mut stats = ScrapeStats::default();
+
+    let get_groups = || run_db(&db, |db| db.get_groups());
+    let mut groups = get_groups().await.unwrap_or_default();
+    if groups.is_empty() {
+        log::info!("No student groups in the database, scraping them first");
+        scrape_all_groups(db.clone(), client).await;
+        groups = get_groups().await.unwrap_or_default();
+    }
+
+    for group in groups {
+        match scraping::scrape_timetable(client, &group.uuid).await {
+            Ok(timetable) => match run_db(&db, move |db| db.update_timetable(&timetable)).await {
+                Ok(diff) => {
+                    stats.scraped += 1;
+                    if !diff.is_empty() {
+                        stats.changed += 1;
+                    }
+                }
+                Err(e) => {
+                    log::error!("Could not store timetable of group {}: {e}", group.uuid);
+                    stats.failed += 1;
+                }
+            },
+            Err(e) => {
+                log::warn!("Could not scrape timetable of group {}: {e}", group.uuid);
+                stats.failed += 1;
+            }
+        }
+    }
+
+    stats.duration = start.elapsed();
+    stats
+}
 
 /// Get all university faculties cron job, runs every 1 September
-pub fn schedule_scrape_faculties(timer: &DelayTimer, db: Arc<Mutex<Database>>) {
+pub fn schedule_scrape_faculties(timer: &DelayTimer, db: Database, client: Arc<dyn RudnClient>) {
     let _ = timer
         .insert_task(
             TaskBuilder::default()
+                .set_task_id(1)
                 .set_frequency_repeated_by_cron_str("0 0 0 1 9 *")
                 .spawn_async_routine(move || {
-                    let _db = db.clone();
+                    let db = db.clone();
+                    let client = client.clone();
                     async move {
                         log::info!("Running cron job \"0 0 0 1 9 *\"");
                         log::info!("Scraping university faculties");
-                        // let faculties = scraping::scrape_faculties().await;
-                        // let mut db = db.lock().unwrap();
-                        // db.update_faculties(&faculties);
+                        let stats = scrape_all_faculties(db, client.as_ref()).await;
+                        log::info!("Faculties: {stats}");
                     }
                 })
                 .unwrap(),
@@ -26,17 +160,20 @@ pub fn schedule_scrape_faculties(timer: &DelayTimer, db: Arc<Mutex<Database>>) {
 }
 
 /// Get all studeng groups cron job, runs every 1st day of the month
-pub fn schedule_scrape_groups(timer: &DelayTimer, db: Arc<Mutex<Database>>) {
+pub fn schedule_scrape_groups(timer: &DelayTimer, db: Database, client: Arc<dyn RudnClient>) {
     let _ = timer
         .insert_task(
             TaskBuilder::default()
+                .set_task_id(2)
                 .set_frequency_repeated_by_cron_str("0 0 0 1 * *")
                 .spawn_async_routine(move || {
-                    let _db = db.clone();
+                    let db = db.clone();
+                    let client = client.clone();
                     async move {
                         log::info!("Running cron job \"0 0 0 1 * *\"");
                         log::info!("Scraping student groups");
-                        // scraping::scrape_groups().await;
+                        let stats = scrape_all_groups(db, client.as_ref()).await;
+                        log::info!("Student groups: {stats}");
                     }
                 })
                 .unwrap(),
@@ -45,17 +182,41 @@ pub fn schedule_scrape_groups(timer: &DelayTimer, db: Arc<Mutex<Database>>) {
 }
 
 /// Get all studeng groups' current timetables cron job, runs every Monday
-pub fn schedule_scrape_timetables(timer: &DelayTimer, db: Arc<Mutex<Database>>) {
+pub fn schedule_scrape_timetables(timer: &DelayTimer, db: Database, client: Arc<dyn RudnClient>) {
     let _ = timer
         .insert_task(
             TaskBuilder::default()
+                .set_task_id(3)
                 .set_frequency_repeated_by_cron_str("0 0 0 * * 1")
                 .spawn_async_routine(move || {
-                    let _db = db.clone();
+                    let db = db.clone();
+                    let client = client.clone();
                     async move {
                         log::info!("Running cron job \"0 0 0 * * 1\"");
                         log::info!("Scraping current timetables");
-                        // scraping::scrape_timetables().await
+                        let stats = scrape_all_timetables(db, client.as_ref()).await;
+                        log::info!("Timetables: {stats}");
+                    }
+                })
+                .unwrap(),
+        )
+        .unwrap();
+}
+
+/// Deliver timetable changes to webhooks cron job, runs every minute
+pub fn schedule_deliver_webhooks(timer: &DelayTimer, db: Database) {
+    let _ = timer
+        .insert_task(
+            TaskBuilder::default()
+                .set_task_id(4)
+                .set_frequency_repeated_by_cron_str("0 * * * * *")
+                .spawn_async_routine(move || {
+                    let db = db.clone();
+                    async move {
+                        let stats = webhooks::deliver_pending(&db).await;
+                        if stats.delivered + stats.failed + stats.dead > 0 {
+                            log::info!("Webhooks: {stats}");
+                        }
                     }
                 })
                 .unwrap(),
@@ -1,190 +1,371 @@
-use std::collections::HashMap;
+use crate::{database::models::*, lessons};
+use chrono::{NaiveDate, NaiveTime};
+use scraper::{node::Node, ElementRef, Html, Selector};
 
-use crate::database::models::*;
-use chrono::{Datelike, NaiveTime};
-use scraper::{Html, Selector};
+pub mod client;
+pub use client::{client_from_env, RudnClient};
+pub mod error;
+pub use error::{ScrapeError, ScrapeResult};
+pub mod throttling;
 
-pub async fn scrape_faculties() -> Option<Vec<Faculty>> {
+pub async fn scrape_faculties(client: &dyn RudnClient) -> ScrapeResult<Vec<Faculty>> {
     log::info!("Scraping faculties");
-    let response = reqwest::get("https://www.rudn.ru/education/schedule")
-        .await
-        .map_err(|e| {
-            log::error!("{e:?}");
-            e
-        })
-        .ok()?
-        .text()
-        .await
-        .map_err(|e| {
-            log::error!("{e:?}");
-            e
-        })
-        .ok()?;
-
-    let document = Html::parse_document(&response);
+    let response = client.faculties_page().await?;
     log::debug!("Got the webpage");
 
+    parse_faculties(&response)
+}
+
+pub fn parse_faculties(page: &str) -> ScrapeResult<Vec<Faculty>> {
+    let document = Html::parse_document(page);
+
     // Select 'select' element for faculties
-    let faculty_select_element_selector = Selector::parse(r#"select[name="facultet"]"#).ok()?;
-    let faculty_select_element = document.select(&faculty_select_element_selector).next()?;
+    let faculty_select_element_selector = r#"select[name="facultet"]"#;
+    let faculty_select_element = document
+        .select(&Selector::parse(faculty_select_element_selector).unwrap())
+        .next()
+        .ok_or_else(|| ScrapeError::layout_changed(faculty_select_element_selector))?;
 
-    let faculties: Vec<Faculty> = faculty_select_element
-        .select(&Selector::parse("option").ok()?)
+    let faculties = faculty_select_element
+        .select(&Selector::parse("option").unwrap())
         .skip(1) // Skip the first element because it is a default option
-        .map(|el| {
-            let name = el.text().next().unwrap().trim();
-            let el = el.value();
-            let uuid = el.attr("value").unwrap();
-            Faculty {
+        .enumerate()
+        .map(|(i, el)| {
+            let context = || format!("faculty option #{}: {}", i + 1, el.html());
+            let name = el
+                .text()
+                .next()
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .ok_or_else(|| ScrapeError::parse(context(), "no faculty name"))?;
+            let uuid = el
+                .value()
+                .attr("value")
+                .ok_or_else(|| ScrapeError::parse(context(), "no 'value' attribute"))?;
+            Ok(Faculty {
                 uuid: String::from(uuid),
                 name: String::from(name),
-            }
+            })
         })
-        .collect();
+        .collect::<ScrapeResult<Vec<_>>>()?;
 
     log::debug!("Successfully retreived data from the timetable webpage");
 
-    Some(faculties)
+    if faculties.is_empty() {
+        Err(ScrapeError::NoData(String::from("faculties")))
+    } else {
+        Ok(faculties)
+    }
 }
 
-pub async fn scrape_group(faculty_uuid: &Uuid) -> Option<Vec<Group>> {
+pub async fn scrape_group(
+    client: &dyn RudnClient,
+    faculty_uuid: &Uuid,
+) -> ScrapeResult<Vec<Group>> {
     log::info!("Scraping groups for faculty: {faculty_uuid:?}");
-    let mut payload = HashMap::new();
-    payload.insert("facultet", faculty_uuid.clone());
-    payload.insert("level", String::from(""));
-    payload.insert("action", String::from("filterData"));
-
-    let groups = match reqwest::Client::new()
-        .post("https://www.rudn.ru/api/v1/education/schedule")
-        .json(&payload)
-        .send()
-        .await
-    {
-        Ok(resp) => {
-            log::debug!("Got json response from RUDN API");
-
-            let parsed = json::parse(&resp.text().await.unwrap())
-                .map_err(|e| log::error!("Error while parsing json response: {e:?}"))
-                .ok()?;
-
-            match &parsed["data"]["elements"]["group"]["list"] {
-                json::JsonValue::Array(vec) => {
-                    let mut groups = vec![];
-                    for el in vec {
-                        let group = Group {
-                            uuid: el["value"].as_str().unwrap().to_string(),
-                            name: el["name"].as_str().unwrap().to_string(),
-                            faculty: faculty_uuid.clone(),
-                        };
-                        groups.push(group);
-                    }
+    let response = client.groups_json(faculty_uuid).await?;
+    log::debug!("Got json response from RUDN API");
 
-                    groups
-                }
-                t => {
-                    log::error!("Unexpected group list format: {t:?}");
-                    return None;
-                }
-            }
-        }
+    parse_groups(&response, faculty_uuid)
+}
 
-        Err(e) => {
-            log::error!("{e:?}");
-            return None;
+pub fn parse_groups(response: &str, faculty_uuid: &Uuid) -> ScrapeResult<Vec<Group>> {
+    let parsed =
+        json::parse(response).map_err(|e| ScrapeError::parse("groups json response", e))?;
+
+    let groups = match &parsed["data"]["elements"]["group"]["list"] {
+        json::JsonValue::Array(vec) => vec
+            .iter()
+            .enumerate()
+            .map(|(i, el)| {
+                let field = |name: &str| {
+                    el[name].as_str().map(String::from).ok_or_else(|| {
+                        ScrapeError::parse(
+                            format!("group #{}: {}", i + 1, el.dump()),
+                            format!("no '{name}' string"),
+                        )
+                    })
+                };
+                Ok(Group {
+                    uuid: field("value")?,
+                    name: field("name")?,
+                    faculty: faculty_uuid.clone(),
+                })
+            })
+            .collect::<ScrapeResult<Vec<_>>>()?,
+        t => {
+            log::error!("Unexpected group list format: {t:?}");
+            return Err(ScrapeError::layout_changed("data.elements.group.list"));
         }
     };
 
     log::debug!("Successfully retreived data from RUDN API");
 
     if groups.is_empty() {
-        None
+        Err(ScrapeError::NoData(format!(
+            "groups of faculty {faculty_uuid}"
+        )))
     } else {
-        Some(groups)
+        Ok(groups)
     }
 }
 
-pub async fn scrape_timetable(group_uuid: &Uuid) -> Option<HashMap<Day, Vec<Event>>> {
+pub async fn scrape_timetable(
+    client: &dyn RudnClient,
+    group_uuid: &Uuid,
+) -> ScrapeResult<ScrapedTimetable> {
     log::info!("Scraping timetable for group: {group_uuid:?}");
-    let response = reqwest::get(format!(
-        "https://www.rudn.ru/api/v1/education/schedule?group={group_uuid}"
-    ))
-    .await
-    .map_err(|e| {
-        log::error!("{e:?}");
-        e
-    })
-    .ok()?
-    .text()
-    .await
-    .map_err(|e| {
-        log::error!("{e:?}");
-        e
-    })
-    .ok()?;
-
-    let document = Html::parse_document(&response);
+    let response = client.timetable_page(group_uuid).await?;
     log::debug!("Got the webpage part");
 
-    let current_week_table = {
-        let curr_week_number = chrono::Local::now().iso_week().week();
-        let current_week_tabpanel_selector =
-            Selector::parse(&format!("#tab__level-{curr_week_number}")).unwrap();
-        let current_week_tabpanel = document.select(&current_week_tabpanel_selector).next()?;
-        current_week_tabpanel
+    parse_timetable(&response, group_uuid, lessons::now().date_naive())
+}
+
+/// Parses every week of the timetable webpage, weeks without classes included.
+/// Week numbers are resolved to the weeks closest to `today`
+pub fn parse_timetable(
+    page: &str,
+    group_uuid: &Uuid,
+    today: NaiveDate,
+) -> ScrapeResult<ScrapedTimetable> {
+    let document = Html::parse_document(page);
+    let mut timetable = ScrapedTimetable::new(group_uuid);
+
+    // Every week of the semester is rendered in its own tab panel
+    let week_tabpanel_selector = r#"[id^="tab__level-"]"#;
+    let week_tabpanels = document
+        .select(&Selector::parse(week_tabpanel_selector).unwrap())
+        .collect::<Vec<_>>();
+    if week_tabpanels.is_empty() {
+        return Err(ScrapeError::layout_changed(week_tabpanel_selector));
+    }
+
+    for week_tabpanel in week_tabpanels {
+        let week_id = week_tabpanel.value().id().unwrap_or_default();
+        let week_start = week_id
+            .strip_prefix("tab__level-")
+            .and_then(|number| number.parse::<u32>().ok())
+            .and_then(|week| week_start_for(week, today))
+            .ok_or_else(|| ScrapeError::parse(format!("week tab '{week_id}'"), "no week number"))?;
+
+        let events = timetable.weeks.entry(week_start).or_default();
+        if let Some(week_table) = week_tabpanel
             .select(&Selector::parse("table").unwrap())
-            .next()?
+            .next()
+        {
+            parse_week_table(week_table, week_start, group_uuid, events)?;
+        }
+    }
+
+    log::debug!("Successfully parsed data from the timetable webpage");
+
+    Ok(timetable)
+}
+
+/// Parses a timetable table of a single week into `events`
+fn parse_week_table(
+    week_table: ElementRef,
+    week_start: NaiveDate,
+    group_uuid: &Uuid,
+    events: &mut Vec<Event>,
+) -> ScrapeResult<()> {
+    // Classes of an unknown day, e.g. Sunday, are skipped until the next known one
+    let mut day = Some(Day::Monday);
+    let mut time: Option<(NaiveTime, NaiveTime)> = None;
+
+    for el in week_table.select(&Selector::parse("tr").unwrap()) {
+        match el.select(&Selector::parse("th").unwrap()).next() {
+            Some(th) => {
+                let weekday = th.text().collect::<String>();
+                day = Day::from_russian(weekday.trim()).ok();
+                if day.is_none() {
+                    log::warn!(
+                        "Skipping classes of unknown weekday in the week of {week_start}, row {}",
+                        el.html()
+                    );
+                }
+            }
+            None => {
+                let Some(day) = day else {
+                    continue;
+                };
+                let context = || format!("week of {week_start}, {day:?}, row {}", el.html());
+                if let Some(time_el) = el
+                    .select(&Selector::parse(r#".edss__table-time"#).unwrap())
+                    .next()
+                {
+                    let time_text = time_el.text().collect::<String>();
+                    let parse_time = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M");
+                    time = match time_text.split_once('-') {
+                        Some((start, end)) => Some((
+                            parse_time(start).map_err(|e| ScrapeError::parse(context(), e))?,
+                            parse_time(end).map_err(|e| ScrapeError::parse(context(), e))?,
+                        )),
+                        None => {
+                            return Err(ScrapeError::parse(
+                                context(),
+                                format!("unexpected time '{time_text}'"),
+                            ))
+                        }
+                    };
+                }
+
+                if let Some(subj_el) = el
+                    .select(&Selector::parse(r#".edss__table-subj"#).unwrap())
+                    .next()
+                {
+                    let (start_time, end_time) =
+                        time.ok_or_else(|| ScrapeError::parse(context(), "no time of the class"))?;
+                    let details = parse_event_details(el, subj_el);
+                    let event = Event {
+                        name: details.name,
+                        day,
+                        start_time,
+                        end_time,
+                        student_group: group_uuid.clone(),
+                        week_start,
+                        room: details.room,
+                        teacher: details.teacher,
+                        kind: details.kind,
+                        subgroup: details.subgroup,
+                        link: details.link,
+                    };
+
+                    events.push(event);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Structured information about a class extracted from a timetable row
+#[derive(Debug, Default)]
+struct EventDetails {
+    name: String,
+    room: Option<String>,
+    teacher: Option<String>,
+    kind: Option<EventKind>,
+    subgroup: Option<String>,
+    link: Option<String>,
+}
+
+/// Extracts class details from a timetable row.
+/// The subject cell starts with the subject name, the rest of its text lines
+/// and the other cells of the row contain class kind, room, lecturer and subgroup
+/// in no particular order, so every line is recognised by its content
+fn parse_event_details(row: ElementRef, subj_el: ElementRef) -> EventDetails {
+    let mut lines = text_lines(subj_el).into_iter();
+
+    let mut details = EventDetails {
+        name: lines.next().unwrap_or_default().to_string(),
+        ..Default::default()
     };
 
-    let mut day = Day::Monday;
-    let mut time: Vec<NaiveTime> = vec![];
+    let cell_selector = Selector::parse("td").unwrap();
+    let time_selector = Selector::parse(".edss__table-time").unwrap();
+    let other_cells = row
+        .select(&cell_selector)
+        // Skip the cells holding the subject and the time, they are parsed separately
+        .filter(|cell| {
+            cell.id() != subj_el.id() && !subj_el.ancestors().any(|node| node.id() == cell.id())
+        })
+        .filter(|cell| {
+            !cell
+                .value()
+                .classes()
+                .any(|class| class == "edss__table-time")
+                && cell.select(&time_selector).next().is_none()
+        })
+        .flat_map(text_lines);
+
+    for line in lines.chain(other_cells) {
+        let line = line.as_str();
+        // Lines may hold several fields separated by commas, e.g. "Лекция, ауд. 101"
+        for part in line.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let lowercase = part.to_lowercase();
+            if let Some(kind) = EventKind::from_russian(part) {
+                details.kind.get_or_insert(kind);
+            } else if lowercase.contains("подгр") {
+                // Keep only the subgroup number when there is one, e.g. "1 подгруппа" -> "1"
+                let number = part
+                    .split(|c: char| !c.is_ascii_digit())
+                    .find(|number| !number.is_empty());
+                details
+                    .subgroup
+                    .get_or_insert_with(|| number.unwrap_or(part).to_string());
+            } else if let Some(room) = ["ауд.", "аудитория", "каб."].iter().find_map(|prefix| {
+                lowercase
+                    .strip_prefix(prefix)
+                    .map(|_| &part[prefix.len()..])
+            }) {
+                details.room.get_or_insert_with(|| room.trim().to_string());
+            } else if is_person_name(part) {
+                details.teacher.get_or_insert_with(|| part.to_string());
+            }
+        }
+    }
 
-    let classes = current_week_table
-        .select(&Selector::parse("tr").unwrap())
-        .fold(HashMap::new(), |mut map: HashMap<Day, Vec<Event>>, el| {
-            match el.select(&Selector::parse("th").unwrap()).next() {
-                Some(el) => {
-                    if let Ok(d) = Day::from_russian(&el.inner_html()) {
-                        day = d;
+    details.link = row
+        .select(&Selector::parse("a[href]").unwrap())
+        .filter_map(|a| a.value().attr("href"))
+        .find(|href| href.starts_with("http"))
+        .map(String::from);
+
+    details
+}
+
+/// Splits text of an element into trimmed non-empty lines.
+/// Inline markup like `<b>` is kept within a line, while `<br>` and block elements break it
+fn text_lines(el: ElementRef) -> Vec<String> {
+    fn collect(el: ElementRef, lines: &mut Vec<String>) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(text) => lines.last_mut().unwrap().push_str(text),
+                Node::Element(child_el) => {
+                    let breaks_line = matches!(child_el.name(), "br" | "div" | "p" | "li");
+                    if breaks_line {
+                        lines.push(String::new());
                     }
-                }
-                None => {
-                    if let Some(time_el) = el
-                        .select(&Selector::parse(r#".edss__table-time"#).unwrap())
-                        .next()
-                    {
-                        time = time_el
-                            .inner_html()
-                            .split(" - ")
-                            .map(|el| NaiveTime::parse_from_str(el, "%H:%M").unwrap())
-                            .collect::<Vec<_>>();
+                    if let Some(child) = ElementRef::wrap(child) {
+                        collect(child, lines);
                     }
-
-                    if let Some(name_el) = el
-                        .select(&Selector::parse(r#".edss__table-subj"#).unwrap())
-                        .next()
-                    {
-                        let event = Event {
-                            name: name_el.inner_html(),
-                            day,
-                            start_time: time[0],
-                            end_time: time[1],
-                            student_group: group_uuid.clone(),
-                        };
-
-                        map.entry(day)
-                            .and_modify(|events| events.push(event.clone()))
-                            .or_insert_with(|| vec![event]);
+                    if breaks_line {
+                        lines.push(String::new());
                     }
                 }
+                _ => {}
             }
-            map
-        });
+        }
+    }
 
-    log::debug!("Successfully parsed data from the timetable webpage");
+    let mut lines = vec![String::new()];
+    collect(el, &mut lines);
 
-    if classes.is_empty() {
-        None
-    } else {
-        Some(classes)
-    }
+    lines
+        .iter()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Checks whether the text looks like a name with initials, e.g. "Иванов И.И." or "Иванов И. И."
+fn is_person_name(text: &str) -> bool {
+    let mut words = text.split_whitespace();
+    let Some(surname) = words.next() else {
+        return false;
+    };
+    let initials = words.collect::<String>();
+
+    surname.chars().next().is_some_and(char::is_uppercase)
+        && !initials.is_empty()
+        && initials.split_terminator('.').all(|initial| {
+            let mut chars = initial.chars();
+            chars.next().is_some_and(char::is_uppercase) && chars.next().is_none()
+        })
+        && initials.ends_with('.')
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,136 @@
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use super::{
+    error::{ScrapeError, ScrapeResult},
+    throttling::{Limits, ThrottledClient},
+};
+use crate::database::models::Uuid;
+
+const DEFAULT_BASE_URL: &str = "https://www.rudn.ru";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Source of the RUDN schedule webpages, so scraping can run against saved pages
+#[async_trait]
+pub trait RudnClient: Send + Sync {
+    /// Schedule webpage listing all faculties
+    async fn faculties_page(&self) -> ScrapeResult<String>;
+    /// JSON response of the schedule API listing groups of the faculty
+    async fn groups_json(&self, faculty_uuid: &Uuid) -> ScrapeResult<String>;
+    /// Webpage part with every week of the group's timetable
+    async fn timetable_page(&self, group_uuid: &Uuid) -> ScrapeResult<String>;
+}
+
+/// Client requesting the RUDN website
+pub struct HttpClient {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl HttpClient {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Could not build HTTP client"),
+        }
+    }
+
+    async fn text(request: reqwest::RequestBuilder) -> ScrapeResult<String> {
+        Ok(request.send().await?.error_for_status()?.text().await?)
+    }
+}
+
+#[async_trait]
+impl RudnClient for HttpClient {
+    async fn faculties_page(&self) -> ScrapeResult<String> {
+        Self::text(
+            self.client
+                .get(format!("{}/education/schedule", self.base_url)),
+        )
+        .await
+    }
+
+    async fn groups_json(&self, faculty_uuid: &Uuid) -> ScrapeResult<String> {
+        let payload = json!({
+            "facultet": faculty_uuid,
+            "level": "",
+            "action": "filterData",
+        });
+        Self::text(
+            self.client
+                .post(format!("{}/api/v1/education/schedule", self.base_url))
+                .json(&payload),
+        )
+        .await
+    }
+
+    async fn timetable_page(&self, group_uuid: &Uuid) -> ScrapeResult<String> {
+        Self::text(
+            self.client
+                .get(format!("{}/api/v1/education/schedule", self.base_url))
+                .query(&[("group", group_uuid)]),
+        )
+        .await
+    }
+}
+
+/// Client reading saved webpages from a directory laid out as
+/// `faculties.html`, `groups/{faculty_uuid}.json` and `timetables/{group_uuid}.html`
+pub struct FixtureClient {
+    dir: PathBuf,
+}
+
+impl FixtureClient {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn read(&self, path: PathBuf) -> ScrapeResult<String> {
+        let path = self.dir.join(path);
+        std::fs::read_to_string(&path).map_err(|e| {
+            ScrapeError::Network(format!("Could not read fixture {}: {e}", path.display()))
+        })
+    }
+}
+
+#[async_trait]
+impl RudnClient for FixtureClient {
+    async fn faculties_page(&self) -> ScrapeResult<String> {
+        self.read(PathBuf::from("faculties.html"))
+    }
+
+    async fn groups_json(&self, faculty_uuid: &Uuid) -> ScrapeResult<String> {
+        self.read(PathBuf::from("groups").join(format!("{faculty_uuid}.json")))
+    }
+
+    async fn timetable_page(&self, group_uuid: &Uuid) -> ScrapeResult<String> {
+        self.read(PathBuf::from("timetables").join(format!("{group_uuid}.html")))
+    }
+}
+
+/// Creates a client reading saved webpages from `RUDN_FIXTURES_DIR` when it is set,
+/// otherwise requesting `RUDN_BASE_URL` (the RUDN website by default).
+/// Either one is throttled within the limits read by [`Limits::from_env`]
+pub fn client_from_env() -> Arc<dyn RudnClient> {
+    let inner: Arc<dyn RudnClient> = if let Ok(dir) = env::var("RUDN_FIXTURES_DIR") {
+        log::info!("Scraping saved webpages from {dir}");
+        Arc::new(FixtureClient::new(dir))
+    } else {
+        let base_url = env::var("RUDN_BASE_URL").unwrap_or_else(|_| String::from(DEFAULT_BASE_URL));
+        log::info!("Scraping {base_url}");
+        Arc::new(HttpClient::new(base_url))
+    };
+
+    let limits = Limits::from_env();
+    log::info!(
+        "Sending at most {} requests per second, {} at once",
+        limits.rate,
+        limits.concurrency
+    );
+    Arc::new(ThrottledClient::new(inner, limits))
+}
@@ -0,0 +1,63 @@
+use std::{error::Error, fmt::Display};
+
+#[derive(Clone, Debug)]
+pub enum ScrapeError {
+    /// RUDN website could not be reached
+    Network(String),
+    /// RUDN website did not respond in time
+    Timeout,
+    /// RUDN website responded with an unsuccessful status code
+    HttpStatus(u16),
+    /// Expected element is missing from the page, its layout has probably changed
+    LayoutChanged { selector: String },
+    /// Element is present but its content could not be parsed
+    Parse { context: String, message: String },
+    /// Page was parsed successfully but contains nothing
+    NoData(String),
+}
+
+impl ScrapeError {
+    pub fn layout_changed(selector: impl Into<String>) -> Self {
+        Self::LayoutChanged {
+            selector: selector.into(),
+        }
+    }
+
+    pub fn parse(context: impl Into<String>, message: impl Display) -> Self {
+        Self::Parse {
+            context: context.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ScrapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Network(msg) => write!(f, "Could not reach RUDN website: {msg}"),
+            Self::Timeout => write!(f, "RUDN website did not respond in time"),
+            Self::HttpStatus(status) => write!(f, "RUDN website responded with status {status}"),
+            Self::LayoutChanged { selector } => {
+                write!(f, "Element '{selector}' is missing from RUDN webpage")
+            }
+            Self::Parse { context, message } => write!(f, "Could not parse {context}: {message}"),
+            Self::NoData(what) => write!(f, "RUDN webpage contains no {what}"),
+        }
+    }
+}
+
+impl Error for ScrapeError {}
+
+impl From<reqwest::Error> for ScrapeError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else if let Some(status) = e.status() {
+            Self::HttpStatus(status.as_u16())
+        } else {
+            Self::Network(e.to_string())
+        }
+    }
+}
+
+pub type ScrapeResult<T> = Result<T, ScrapeError>;
@@ -0,0 +1,263 @@
+use super::*;
+use client::FixtureClient;
+use std::collections::HashMap;
+
+const FACULTY: &str = "f0a1b2c3-0000-4000-8000-000000000001";
+const GROUP: &str = "9b1f3e2a-0000-4000-8000-000000000101";
+
+fn fixtures() -> FixtureClient {
+    FixtureClient::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures"))
+}
+
+fn fixture(path: &str) -> String {
+    std::fs::read_to_string(format!("{}/fixtures/{path}", env!("CARGO_MANIFEST_DIR"))).unwrap()
+}
+
+/// Day within the weeks of the timetable fixture, so their numbers resolve to October 2026
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, 7).unwrap()
+}
+
+/// Events of every scraped week by their day, earlier weeks first
+fn by_day(timetable: &ScrapedTimetable) -> HashMap<Day, Vec<Event>> {
+    let mut weeks = timetable.weeks.keys().collect::<Vec<_>>();
+    weeks.sort();
+    let mut by_day: HashMap<Day, Vec<Event>> = HashMap::new();
+    for week_start in weeks {
+        for event in &timetable.weeks[week_start] {
+            by_day.entry(event.day).or_default().push(event.clone());
+        }
+    }
+    by_day
+}
+
+#[actix_web::test]
+async fn scrapes_faculties() {
+    let faculties = scrape_faculties(&fixtures()).await.unwrap();
+
+    let faculties = faculties
+        .iter()
+        .map(|faculty| (faculty.uuid.as_str(), faculty.name.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        faculties,
+        [
+            (
+                FACULTY,
+                "Факультет физико-математических и естественных наук"
+            ),
+            (
+                "f0a1b2c3-0000-4000-8000-000000000002",
+                "Инженерная академия"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn faculties_page_without_select_is_rejected() {
+    assert!(matches!(
+        parse_faculties(&fixture("faculties_without_select.html")),
+        Err(ScrapeError::LayoutChanged { .. })
+    ));
+}
+
+#[test]
+fn faculty_without_uuid_is_rejected() {
+    match parse_faculties(&fixture("faculties_without_value.html")) {
+        Err(ScrapeError::Parse { context, .. }) => assert!(context.contains("Инженерная академия")),
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[actix_web::test]
+async fn scrapes_groups() {
+    let groups = scrape_group(&fixtures(), &FACULTY.to_string())
+        .await
+        .unwrap();
+
+    let names = groups
+        .iter()
+        .map(|group| group.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["НПИбд-01-22", "НПИбд-02-22"]);
+    assert_eq!(groups[0].uuid, GROUP);
+    assert!(groups.iter().all(|group| group.faculty == FACULTY));
+}
+
+#[actix_web::test]
+async fn faculty_without_groups_is_rejected() {
+    let faculty = String::from("f0a1b2c3-0000-4000-8000-000000000002");
+    assert!(matches!(
+        scrape_group(&fixtures(), &faculty).await,
+        Err(ScrapeError::NoData(_))
+    ));
+}
+
+#[test]
+fn malformed_groups_responses_are_rejected() {
+    let faculty = FACULTY.to_string();
+    assert!(matches!(
+        parse_groups(&fixture("groups/unexpected-format.json"), &faculty),
+        Err(ScrapeError::LayoutChanged { .. })
+    ));
+    assert!(matches!(
+        parse_groups(&fixture("groups/not-json.json"), &faculty),
+        Err(ScrapeError::Parse { .. })
+    ));
+}
+
+#[test]
+fn group_without_uuid_is_rejected() {
+    match parse_groups(&fixture("groups/missing-value.json"), &FACULTY.to_string()) {
+        Err(ScrapeError::Parse { context, message }) => {
+            assert!(context.starts_with("group #2"));
+            assert!(message.contains("value"));
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[actix_web::test]
+async fn missing_fixture_is_an_error() {
+    assert!(matches!(
+        scrape_timetable(&fixtures(), &String::from("unknown-group")).await,
+        Err(ScrapeError::Network(_))
+    ));
+}
+
+#[test]
+fn parses_every_week_of_timetable() {
+    let page = fixture(&format!("timetables/{GROUP}.html"));
+    let timetable = by_day(&parse_timetable(&page, &GROUP.to_string(), today()).unwrap());
+
+    let week_41 = NaiveDate::from_ymd_opt(2026, 10, 5).unwrap();
+    let week_42 = NaiveDate::from_ymd_opt(2026, 10, 12).unwrap();
+
+    let monday = &timetable[&Day::Monday];
+    assert_eq!(monday.len(), 3);
+    assert_eq!(
+        monday
+            .iter()
+            .filter(|event| event.week_start == week_41)
+            .count(),
+        2
+    );
+    assert_eq!(
+        monday
+            .iter()
+            .filter(|event| event.week_start == week_42)
+            .count(),
+        1
+    );
+    assert_eq!(timetable[&Day::Wednesday].len(), 1);
+    assert!(!timetable.contains_key(&Day::Tuesday));
+}
+
+#[actix_web::test]
+async fn scrapes_timetable_of_the_group() {
+    let timetable = scrape_timetable(&fixtures(), &GROUP.to_string())
+        .await
+        .unwrap();
+    assert_eq!(timetable.group, GROUP);
+    assert_eq!(timetable.weeks.len(), 2);
+}
+
+#[test]
+fn parses_class_details() {
+    let page = fixture(&format!("timetables/{GROUP}.html"));
+    let timetable = by_day(&parse_timetable(&page, &GROUP.to_string(), today()).unwrap());
+
+    let lecture = &timetable[&Day::Monday][0];
+    assert_eq!(lecture.name, "Математический анализ");
+    assert_eq!(
+        lecture.start_time,
+        NaiveTime::from_hms_opt(9, 0, 0).unwrap()
+    );
+    assert_eq!(
+        lecture.end_time,
+        NaiveTime::from_hms_opt(10, 20, 0).unwrap()
+    );
+    assert_eq!(lecture.kind, Some(EventKind::Lecture));
+    assert_eq!(lecture.room.as_deref(), Some("101"));
+    assert_eq!(lecture.teacher.as_deref(), Some("Иванов И.И."));
+    assert_eq!(lecture.subgroup, None);
+    assert_eq!(lecture.student_group, GROUP);
+
+    let lab = &timetable[&Day::Monday][1];
+    assert_eq!(lab.name, "Программирование");
+    assert_eq!(lab.kind, Some(EventKind::Lab));
+    assert_eq!(lab.room.as_deref(), Some("315"));
+    assert_eq!(lab.teacher.as_deref(), Some("Петрова А. С."));
+    assert_eq!(lab.subgroup.as_deref(), Some("1"));
+
+    let seminar = &timetable[&Day::Wednesday][0];
+    assert_eq!(seminar.kind, Some(EventKind::Seminar));
+    assert_eq!(seminar.link.as_deref(), Some("https://rudn.zoom.us/j/123"));
+    assert_eq!(seminar.room, None);
+}
+
+#[test]
+fn week_numbers_resolve_across_new_year() {
+    let page = fixture(&format!("timetables/{GROUP}.html"));
+    // Week 41 is closer to the previous October than to the next one
+    let today = NaiveDate::from_ymd_opt(2027, 1, 10).unwrap();
+    let timetable = by_day(&parse_timetable(&page, &GROUP.to_string(), today).unwrap());
+
+    assert!(timetable[&Day::Monday]
+        .iter()
+        .all(|event| event.week_start.format("%Y").to_string() == "2026"));
+}
+
+#[test]
+fn timetable_without_weeks_is_rejected() {
+    let group = GROUP.to_string();
+    assert!(matches!(
+        parse_timetable(&fixture("timetables/without-weeks.html"), &group, today()),
+        Err(ScrapeError::LayoutChanged { .. })
+    ));
+    assert!(matches!(
+        parse_timetable(
+            &fixture("timetables/unexpected-week-id.html"),
+            &group,
+            today()
+        ),
+        Err(ScrapeError::Parse { .. })
+    ));
+}
+
+#[test]
+fn invalid_class_time_is_reported_with_its_row() {
+    let page = fixture("timetables/invalid-time.html");
+    match parse_timetable(&page, &GROUP.to_string(), today()) {
+        Err(ScrapeError::Parse { context, .. }) => {
+            assert!(context.contains("Tuesday"));
+            assert!(context.contains("по договорённости"));
+        }
+        other => panic!("Unexpected result: {other:?}"),
+    }
+}
+
+#[test]
+fn classes_of_unknown_weekday_are_skipped() {
+    let page = fixture("timetables/unknown-weekday.html");
+    let timetable = parse_timetable(&page, &GROUP.to_string(), today()).unwrap();
+
+    let week_41 = NaiveDate::from_ymd_opt(2026, 10, 5).unwrap();
+    let events = timetable.events().collect::<Vec<_>>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        (events[0].name.as_str(), events[0].day),
+        ("Химия", Day::Monday)
+    );
+    assert_eq!(events[0].week_start, week_41);
+}
+
+#[test]
+fn weeks_without_classes_are_kept() {
+    let page = fixture("timetables/without-classes.html");
+    let timetable = parse_timetable(&page, &GROUP.to_string(), today()).unwrap();
+
+    assert_eq!(timetable.weeks.len(), 2);
+    assert!(timetable.events().next().is_none());
+}
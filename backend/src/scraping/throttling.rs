@@ -0,0 +1,248 @@
+use std::{
+    collections::HashMap,
+    env,
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use tokio::sync::{OnceCell, Semaphore};
+
+use super::{
+    client::RudnClient,
+    error::{ScrapeError, ScrapeResult},
+};
+use crate::database::models::Uuid;
+
+/// Limits of the requests sent to the RUDN website
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// Requests per second sent on average
+    pub rate: f64,
+    /// Requests which may be sent at once after a quiet period
+    pub burst: u32,
+    /// Requests awaiting a response at the same time
+    pub concurrency: usize,
+    /// Attempts of a request failing with a transient error, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled with every next one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            rate: 2.0,
+            burst: 5,
+            concurrency: 4,
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+fn parse_env<T: FromStr + PartialOrd + Default>(name: &str, default: T) -> T
+where
+    T::Err: std::fmt::Display,
+{
+    let Ok(value) = env::var(name) else {
+        return default;
+    };
+    match value.trim().parse::<T>() {
+        Ok(parsed) if parsed > T::default() => parsed,
+        Ok(_) => {
+            log::warn!("Ignoring {name}={value}: must be positive");
+            default
+        }
+        Err(e) => {
+            log::warn!("Ignoring {name}={value}: {e}");
+            default
+        }
+    }
+}
+
+impl Limits {
+    /// Reads `SCRAPE_RATE` (requests per second), `SCRAPE_BURST` and `SCRAPE_CONCURRENCY`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            rate: parse_env("SCRAPE_RATE", default.rate),
+            burst: parse_env("SCRAPE_BURST", default.burst),
+            concurrency: parse_env("SCRAPE_CONCURRENCY", default.concurrency),
+            ..default
+        }
+    }
+
+    /// Random delay before the retry following the failed `attempt`, counting from zero.
+    /// Full jitter keeps clients which failed together from retrying together
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64())
+    }
+}
+
+/// Errors which may disappear when the request is repeated
+pub fn is_transient(e: &ScrapeError) -> bool {
+    matches!(
+        e,
+        ScrapeError::Network(_) | ScrapeError::Timeout | ScrapeError::HttpStatus(429 | 500..=599)
+    )
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket refilled with `rate` tokens per second up to `capacity`
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl TokenBucket {
+    /// The bucket is full initially
+    pub fn new(rate: f64, capacity: u32, now: Instant) -> Self {
+        Self {
+            rate,
+            capacity: capacity.into(),
+            bucket: Mutex::new(Bucket {
+                tokens: capacity.into(),
+                refilled_at: now,
+            }),
+        }
+    }
+
+    /// Takes a token if there is one, otherwise returns how long it takes to refill it
+    pub fn try_take(&self, now: Instant) -> Result<(), Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(self.capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate))
+        }
+    }
+
+    pub async fn take(&self) {
+        while let Err(wait) = self.try_take(Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Client protecting the RUDN website from its users: identical concurrent requests
+/// are sent once and share the response, requests are rate limited, their concurrency
+/// is capped and transient failures are retried with exponential backoff
+pub struct ThrottledClient {
+    inner: Arc<dyn RudnClient>,
+    limits: Limits,
+    bucket: TokenBucket,
+    permits: Semaphore,
+    /// Requests awaiting a response by the page they request
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<ScrapeResult<String>>>>>,
+}
+
+impl ThrottledClient {
+    pub fn new(inner: Arc<dyn RudnClient>, limits: Limits) -> Self {
+        Self {
+            inner,
+            bucket: TokenBucket::new(limits.rate, limits.burst, Instant::now()),
+            permits: Semaphore::new(limits.concurrency),
+            in_flight: Mutex::new(HashMap::new()),
+            limits,
+        }
+    }
+
+    /// Joins the request for the same page if it is in flight, sends a new one otherwise
+    async fn coalesced<F, Fut>(&self, key: String, request: F) -> ScrapeResult<String>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ScrapeResult<String>>,
+    {
+        let response = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.contains_key(&key) {
+                log::debug!("Joining the request for {key} in flight");
+            }
+            in_flight.entry(key.clone()).or_default().clone()
+        };
+
+        let result = response
+            .get_or_init(|| self.limited(&key, request))
+            .await
+            .clone();
+
+        // Later requests must get a fresh response
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight
+            .get(&key)
+            .is_some_and(|in_flight| Arc::ptr_eq(in_flight, &response))
+        {
+            in_flight.remove(&key);
+        }
+        result
+    }
+
+    /// Sends the request within the limits, retrying it after transient failures
+    async fn limited<F, Fut>(&self, key: &str, request: F) -> ScrapeResult<String>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ScrapeResult<String>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let result = {
+                let _permit = self.permits.acquire().await.unwrap();
+                self.bucket.take().await;
+                request().await
+            };
+            match result {
+                Err(e) if is_transient(&e) && attempt + 1 < self.limits.max_attempts => {
+                    let delay = self.limits.backoff(attempt);
+                    log::warn!("Request for {key} failed: {e}, retrying in {delay:.2?}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RudnClient for ThrottledClient {
+    async fn faculties_page(&self) -> ScrapeResult<String> {
+        self.coalesced(String::from("faculties"), || self.inner.faculties_page())
+            .await
+    }
+
+    async fn groups_json(&self, faculty_uuid: &Uuid) -> ScrapeResult<String> {
+        self.coalesced(format!("groups:{faculty_uuid}"), || {
+            self.inner.groups_json(faculty_uuid)
+        })
+        .await
+    }
+
+    async fn timetable_page(&self, group_uuid: &Uuid) -> ScrapeResult<String> {
+        self.coalesced(format!("timetable:{group_uuid}"), || {
+            self.inner.timetable_page(group_uuid)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests;
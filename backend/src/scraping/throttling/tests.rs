@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::*;
+
+/// Client answering after a delay, failing with the given errors first
+struct CountingClient {
+    requests: AtomicUsize,
+    failures: Mutex<Vec<ScrapeError>>,
+}
+
+impl CountingClient {
+    fn new(failures: Vec<ScrapeError>) -> Arc<Self> {
+        Arc::new(Self {
+            requests: AtomicUsize::new(0),
+            failures: Mutex::new(failures),
+        })
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+
+    async fn respond(&self, page: &str) -> ScrapeResult<String> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        match self.failures.lock().unwrap().pop() {
+            Some(e) => Err(e),
+            None => Ok(page.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl RudnClient for CountingClient {
+    async fn faculties_page(&self) -> ScrapeResult<String> {
+        self.respond("faculties").await
+    }
+
+    async fn groups_json(&self, faculty_uuid: &Uuid) -> ScrapeResult<String> {
+        self.respond(faculty_uuid).await
+    }
+
+    async fn timetable_page(&self, group_uuid: &Uuid) -> ScrapeResult<String> {
+        self.respond(group_uuid).await
+    }
+}
+
+fn fast_limits() -> Limits {
+    Limits {
+        rate: 1000.0,
+        burst: 100,
+        base_delay: Duration::from_millis(1),
+        ..Limits::default()
+    }
+}
+
+#[actix_web::test]
+async fn identical_requests_are_coalesced() {
+    let inner = CountingClient::new(vec![]);
+    let client = Arc::new(ThrottledClient::new(inner.clone(), fast_limits()));
+
+    let handles = (0..50)
+        .map(|_| {
+            let client = client.clone();
+            actix_web::rt::spawn(async move { client.timetable_page(&String::from("group")).await })
+        })
+        .collect::<Vec<_>>();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap(), "group");
+    }
+    assert_eq!(inner.requests(), 1);
+
+    // Finished requests are not reused
+    client.timetable_page(&String::from("group")).await.unwrap();
+    assert_eq!(inner.requests(), 2);
+}
+
+#[actix_web::test]
+async fn different_requests_are_not_coalesced() {
+    let inner = CountingClient::new(vec![]);
+    let client = ThrottledClient::new(inner.clone(), fast_limits());
+
+    let first = String::from("first");
+    let second = String::from("second");
+    let (first, second) = tokio::join!(client.groups_json(&first), client.groups_json(&second));
+    assert_eq!(first.unwrap(), "first");
+    assert_eq!(second.unwrap(), "second");
+    assert_eq!(inner.requests(), 2);
+}
+
+#[actix_web::test]
+async fn transient_failures_are_retried() {
+    let inner = CountingClient::new(vec![ScrapeError::HttpStatus(503), ScrapeError::Timeout]);
+    let client = ThrottledClient::new(inner.clone(), fast_limits());
+
+    assert_eq!(client.faculties_page().await.unwrap(), "faculties");
+    assert_eq!(inner.requests(), 3);
+}
+
+#[actix_web::test]
+async fn retries_are_limited() {
+    let failures = vec![ScrapeError::Timeout; 10];
+    let inner = CountingClient::new(failures);
+    let client = ThrottledClient::new(inner.clone(), fast_limits());
+
+    assert!(matches!(
+        client.faculties_page().await,
+        Err(ScrapeError::Timeout)
+    ));
+    assert_eq!(inner.requests(), Limits::default().max_attempts as usize);
+}
+
+#[actix_web::test]
+async fn client_errors_are_not_retried() {
+    let inner = CountingClient::new(vec![ScrapeError::HttpStatus(404)]);
+    let client = ThrottledClient::new(inner.clone(), fast_limits());
+
+    assert!(client.faculties_page().await.is_err());
+    assert_eq!(inner.requests(), 1);
+}
+
+#[test]
+fn bucket_allows_bursts_and_refills() {
+    let start = Instant::now();
+    let bucket = TokenBucket::new(2.0, 3, start);
+
+    for _ in 0..3 {
+        assert!(bucket.try_take(start).is_ok());
+    }
+    assert_eq!(bucket.try_take(start), Err(Duration::from_millis(500)));
+
+    let later = start + Duration::from_millis(500);
+    assert!(bucket.try_take(later).is_ok());
+    assert!(bucket.try_take(later).is_err());
+
+    // Tokens do not accumulate over the capacity
+    let much_later = later + Duration::from_secs(60);
+    for _ in 0..3 {
+        assert!(bucket.try_take(much_later).is_ok());
+    }
+    assert!(bucket.try_take(much_later).is_err());
+}
+
+#[test]
+fn backoff_grows_up_to_the_limit() {
+    let limits = Limits::default();
+    for attempt in 0..10 {
+        let ceiling = (limits.base_delay * 2u32.pow(attempt)).min(limits.max_delay);
+        assert!(limits.backoff(attempt) <= ceiling);
+    }
+}
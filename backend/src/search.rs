@@ -0,0 +1,129 @@
+//! Matching of group names typed by users, who often do not know
+//! the exact spelling or type the name on a Latin keyboard layout
+
+use crate::database::models::*;
+
+/// Maximum number of matches returned for a single query
+pub const MAX_MATCHES: usize = 20;
+
+/// How well a group name matches the query, lower is better
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Relevance {
+    Exact,
+    Prefix,
+    Substring,
+    /// Query is within this many edits from the beginning of the name
+    Fuzzy(usize),
+}
+
+/// Lowercases the name, transliterates Cyrillic letters to Latin ones and drops everything
+/// except letters and digits, so "НПИбд-01-22", "npibd 01 22" and "NPIBD0122" are all equal
+pub fn normalize(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars().flat_map(char::to_lowercase) {
+        match transliterate(c) {
+            Some(latin) => normalized.push_str(latin),
+            None if c.is_alphanumeric() => normalized.push(c),
+            None => {}
+        }
+    }
+    normalized
+}
+
+/// Transliteration of a lowercase Cyrillic letter, close to the one used in Russian passports
+fn transliterate(c: char) -> Option<&'static str> {
+    let latin = match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' => "i",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "iu",
+        'я' => "ia",
+        _ => return None,
+    };
+    Some(latin)
+}
+
+/// Rates how well the normalized group name matches the normalized query.
+/// Returns `None` when they have too little in common
+pub fn relevance(query: &str, name: &str) -> Option<Relevance> {
+    if query.is_empty() {
+        return None;
+    }
+    if name == query {
+        return Some(Relevance::Exact);
+    }
+    if name.starts_with(query) {
+        return Some(Relevance::Prefix);
+    }
+    if name.contains(query) {
+        return Some(Relevance::Substring);
+    }
+
+    // Every fourth character of the query may be mistyped
+    let max_distance = query.chars().count() / 4;
+    let query = query.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let distance = prefix_distance(&query, &name);
+    (distance <= max_distance).then_some(Relevance::Fuzzy(distance))
+}
+
+/// Smallest Levenshtein distance between the query and any prefix of the name
+fn prefix_distance(query: &[char], name: &[char]) -> usize {
+    // Distances between the whole query and prefixes of the name of every length
+    let mut row = vec![0; name.len() + 1];
+    for (i, query_char) in query.iter().enumerate() {
+        let previous = row.clone();
+        row[0] = i + 1;
+        for (j, name_char) in name.iter().enumerate() {
+            let substitution = previous[j] + usize::from(query_char != name_char);
+            row[j + 1] = substitution.min(previous[j + 1] + 1).min(row[j] + 1);
+        }
+    }
+    row.into_iter().min().unwrap_or(query.len())
+}
+
+/// Selects groups matching the query, the best matches come first
+pub fn find_groups<T>(query: &str, groups: Vec<(Group, T)>) -> Vec<(Group, T)> {
+    let query = normalize(query);
+    let mut matches = groups
+        .into_iter()
+        .filter_map(|(group, extra)| {
+            relevance(&query, &normalize(&group.name)).map(|rank| (rank, group, extra))
+        })
+        .collect::<Vec<_>>();
+    matches.sort_by(|(a_rank, a, _), (b_rank, b, _)| {
+        a_rank.cmp(b_rank).then_with(|| a.name.cmp(&b.name))
+    });
+    matches
+        .into_iter()
+        .take(MAX_MATCHES)
+        .map(|(_, group, extra)| (group, extra))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
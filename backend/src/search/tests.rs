@@ -0,0 +1,72 @@
+use super::*;
+
+fn group(name: &str) -> (Group, ()) {
+    (
+        Group {
+            uuid: format!("uuid-{name}"),
+            name: name.to_string(),
+            faculty: String::from("faculty"),
+        },
+        (),
+    )
+}
+
+fn names(groups: &[(Group, ())]) -> Vec<&str> {
+    groups
+        .iter()
+        .map(|(group, _)| group.name.as_str())
+        .collect()
+}
+
+#[test]
+fn normalizes_case_separators_and_script() {
+    assert_eq!(normalize("НПИбд-01-22"), "npibd0122");
+    assert_eq!(normalize("npibd 01 22"), "npibd0122");
+    assert_eq!(normalize("NPIBD0122"), "npibd0122");
+    assert_eq!(normalize("Щёлково-Юг"), "shchelkovoiug");
+}
+
+#[test]
+fn ranks_exact_matches_before_prefixes_and_substrings() {
+    let groups = vec![
+        group("НПИбд-01-22"),
+        group("ФНПИбд-01-22"),
+        group("НПИбд-01-22-1"),
+        group("ИВТбд-01-22"),
+    ];
+
+    let found = find_groups("нпибд-01-22", groups);
+    assert_eq!(
+        names(&found),
+        ["НПИбд-01-22", "НПИбд-01-22-1", "ФНПИбд-01-22"]
+    );
+}
+
+#[test]
+fn finds_groups_typed_in_latin() {
+    let groups = vec![group("НПИбд-01-22"), group("НКНбд-01-22")];
+
+    assert_eq!(names(&find_groups("NPIbd", groups)), ["НПИбд-01-22"]);
+}
+
+#[test]
+fn tolerates_typos_in_longer_queries() {
+    let groups = vec![group("НПИбд-01-22"), group("НММбд-02-21")];
+
+    // One mistyped letter and a missing one
+    assert_eq!(
+        names(&find_groups("нпюбд0122", groups.clone())),
+        ["НПИбд-01-22"]
+    );
+    assert_eq!(
+        names(&find_groups("нпбд-01-22", groups.clone())),
+        ["НПИбд-01-22"]
+    );
+    // Short queries must match exactly
+    assert!(find_groups("нпх", groups).is_empty());
+}
+
+#[test]
+fn empty_query_matches_nothing() {
+    assert!(find_groups(" - ", vec![group("НПИбд-01-22")]).is_empty());
+}
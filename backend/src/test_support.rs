@@ -0,0 +1,90 @@
+//! Fixtures shared by the test modules
+
+use chrono::{NaiveDate, NaiveTime};
+
+use crate::database::models::*;
+
+/// Builds events for tests, by default a class "Math" of group "group"
+/// on Monday 2026-10-05 from 09:00 to 10:20 without any details
+pub struct EventBuilder(Event);
+
+impl Event {
+    pub fn builder() -> EventBuilder {
+        EventBuilder(Event {
+            name: String::from("Math"),
+            day: Day::Monday,
+            start_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(10, 20, 0).unwrap(),
+            student_group: String::from("group"),
+            week_start: NaiveDate::from_ymd_opt(2026, 10, 5).unwrap(),
+            room: None,
+            teacher: None,
+            kind: None,
+            subgroup: None,
+            link: None,
+        })
+    }
+}
+
+impl EventBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.0.name = name.to_string();
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.0.student_group = group.to_string();
+        self
+    }
+
+    pub fn week(mut self, week_start: NaiveDate) -> Self {
+        self.0.week_start = week_start;
+        self
+    }
+
+    pub fn day(mut self, day: Day) -> Self {
+        self.0.day = day;
+        self
+    }
+
+    /// Start and end as hours and minutes
+    pub fn time(mut self, start: (u32, u32), end: (u32, u32)) -> Self {
+        self.0.start_time = NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap();
+        self.0.end_time = NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap();
+        self
+    }
+
+    /// Class of 80 minutes starting at the beginning of the hour
+    pub fn starts_at(self, hour: u32) -> Self {
+        self.time((hour, 0), (hour + 1, 20))
+    }
+
+    pub fn room(mut self, room: &str) -> Self {
+        self.0.room = Some(room.to_string());
+        self
+    }
+
+    pub fn teacher(mut self, teacher: &str) -> Self {
+        self.0.teacher = Some(teacher.to_string());
+        self
+    }
+
+    pub fn kind(mut self, kind: EventKind) -> Self {
+        self.0.kind = Some(kind);
+        self
+    }
+
+    pub fn subgroup(mut self, subgroup: &str) -> Self {
+        self.0.subgroup = Some(subgroup.to_string());
+        self
+    }
+
+    pub fn link(mut self, link: &str) -> Self {
+        self.0.link = Some(link.to_string());
+        self
+    }
+
+    pub fn build(self) -> Event {
+        self.0
+    }
+}
@@ -0,0 +1,135 @@
+//! Delivery of timetable changes to subscribed webhooks
+
+use std::fmt::Display;
+
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::{
+    database::{models::*, Database},
+    routes::run_db,
+};
+
+/// Deliveries failing this many times are moved to the dead letters
+const MAX_ATTEMPTS: i32 = 8;
+/// Number of deliveries attempted in a single run
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    delivery: i32,
+    change: &'a TimetableChange,
+}
+
+/// Statistics of a single delivery run
+#[derive(Debug, Default)]
+pub struct DeliveryStats {
+    pub delivered: usize,
+    pub failed: usize,
+    /// Number of deliveries which exhausted their attempts
+    pub dead: usize,
+}
+
+impl Display for DeliveryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} delivered, {} failed, {} dead",
+            self.delivered, self.failed, self.dead
+        )
+    }
+}
+
+/// Signs the payload with the webhook's secret, receivers compare it
+/// with the `X-Timetable-Signature` header to verify the payload came from us
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delay before the next attempt, doubles with every failed attempt up to a day
+fn retry_delay(attempts: i32) -> Duration {
+    Duration::minutes(1 << attempts.clamp(0, 11)).min(Duration::days(1))
+}
+
+/// Attempts every due delivery once, rescheduling failed ones with an exponential backoff
+pub async fn deliver_pending(db: &Database) -> DeliveryStats {
+    let mut stats = DeliveryStats::default();
+    let now = Utc::now().naive_utc();
+    let deliveries = match run_db(db, move |db| db.get_due_deliveries(now, BATCH_SIZE)).await {
+        Ok(deliveries) => deliveries,
+        Err(e) => {
+            log::error!("Could not retrieve due webhook deliveries: {e}");
+            return stats;
+        }
+    };
+
+    let client = reqwest::Client::new();
+    for (delivery, webhook, change) in deliveries {
+        let payload = serde_json::to_vec(&Payload {
+            delivery: delivery.id,
+            change: &change,
+        })
+        .unwrap();
+
+        let result = client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Timetable-Delivery", delivery.id)
+            .header("X-Timetable-Signature", sign(&webhook.secret, &payload))
+            .timeout(std::time::Duration::from_secs(10))
+            .body(payload)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+
+        let (status, attempt_at, error) = match result {
+            Ok(_) => {
+                stats.delivered += 1;
+                (DeliveryStatus::Delivered, now, None)
+            }
+            Err(e) if delivery.attempts + 1 >= MAX_ATTEMPTS => {
+                log::error!(
+                    "Giving up delivering change {} to webhook {} ({}) after {} attempts: {e}",
+                    change.id,
+                    webhook.id,
+                    webhook.url,
+                    delivery.attempts + 1
+                );
+                stats.dead += 1;
+                (DeliveryStatus::Dead, now, Some(e.to_string()))
+            }
+            Err(e) => {
+                log::warn!(
+                    "Could not deliver change {} to webhook {} ({}): {e}",
+                    change.id,
+                    webhook.id,
+                    webhook.url
+                );
+                stats.failed += 1;
+                let next_attempt_at = now + retry_delay(delivery.attempts);
+                (
+                    DeliveryStatus::Pending,
+                    next_attempt_at,
+                    Some(e.to_string()),
+                )
+            }
+        };
+        let id = delivery.id;
+        let update = run_db(db, move |db| {
+            db.update_delivery(id, status, attempt_at, error)
+        });
+        if let Err(e) = update.await {
+            log::error!("Could not store outcome of delivery {}: {e}", delivery.id);
+        }
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,141 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+use super::*;
+
+#[test]
+fn payloads_are_signed_with_hmac_sha256() {
+    // Test case 2 of RFC 4231
+    assert_eq!(
+        sign("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_ne!(sign("other", b"payload"), sign("secret", b"payload"));
+}
+
+#[test]
+fn retry_delay_doubles_up_to_a_day() {
+    assert_eq!(retry_delay(0), Duration::minutes(1));
+    assert_eq!(retry_delay(1), Duration::minutes(2));
+    assert_eq!(retry_delay(5), Duration::minutes(32));
+    assert_eq!(retry_delay(10), Duration::minutes(1024));
+    assert_eq!(retry_delay(11), Duration::days(1));
+    assert_eq!(retry_delay(100), Duration::days(1));
+    assert_eq!(retry_delay(-1), Duration::minutes(1));
+}
+
+/// Fresh migrated SQLite database in a temporary file
+fn database() -> (Database, std::path::PathBuf) {
+    let path = std::env::temp_dir().join(format!("backend_webhooks_{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = Database::connect(path.to_str().unwrap()).unwrap();
+    db.run_migrations().unwrap();
+    (db, path)
+}
+
+/// Receiver answering a single request with 200, returns the request headers and body
+fn receiver() -> (String, JoinHandle<(Vec<String>, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut headers = vec![];
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            headers.push(line);
+        }
+        let length = headers
+            .iter()
+            .find_map(|header| header.strip_prefix("content-length: "))
+            .map_or(0, |length| length.parse().unwrap());
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .into_inner()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        (headers, String::from_utf8(body).unwrap())
+    });
+    (url, handle)
+}
+
+fn event(name: &str) -> Event {
+    Event::builder().name(name).build()
+}
+
+#[actix_web::test]
+async fn pending_deliveries_are_sent_and_failed_ones_rescheduled() {
+    let (db, path) = database();
+    db.update_faculties(&[Faculty {
+        uuid: String::from("faculty"),
+        name: String::from("Faculty"),
+    }])
+    .unwrap();
+    db.update_groups(&[Group {
+        uuid: String::from("group"),
+        name: String::from("Group"),
+        faculty: String::from("faculty"),
+    }])
+    .unwrap();
+
+    let (url, received) = receiver();
+    let webhook = |url: &str| NewWebhook {
+        url: url.to_string(),
+        secret: String::from("secret"),
+        student_group: None,
+        created_at: String::from("2026-10-05T00:00:00"),
+    };
+    db.add_webhook(&webhook(&url)).unwrap();
+    // Nothing listens on the discard port
+    let unreachable = db.add_webhook(&webhook("http://127.0.0.1:9/hook")).unwrap();
+
+    let timetable = |events: &[Event]| {
+        let mut timetable = ScrapedTimetable::new(&String::from("group"));
+        timetable
+            .weeks
+            .insert(events[0].week_start, events.to_vec());
+        timetable
+    };
+    db.update_timetable(&timetable(&[event("Math")])).unwrap();
+    db.update_timetable(&timetable(&[event("Math"), event("Physics")]))
+        .unwrap();
+
+    let stats = deliver_pending(&db).await;
+    assert_eq!((stats.delivered, stats.failed, stats.dead), (1, 1, 0));
+
+    let (headers, body) = received.join().unwrap();
+    assert!(headers.contains(&format!(
+        "x-timetable-signature: {}",
+        sign("secret", body.as_bytes())
+    )));
+    assert!(body.contains("Physics"));
+
+    // The failed delivery is retried later, the delivered one is not sent again
+    let now = Utc::now().naive_utc();
+    let due = db
+        .get_due_deliveries(now + Duration::minutes(2), 10)
+        .unwrap();
+    assert_eq!(due.len(), 1);
+    let (delivery, webhook, _) = &due[0];
+    assert_eq!((webhook.id, delivery.attempts), (unreachable.id, 1));
+    assert!(delivery.last_error.is_some());
+
+    // The last attempt moves the delivery to the dead letters
+    for _ in 1..MAX_ATTEMPTS - 1 {
+        db.update_delivery(delivery.id, DeliveryStatus::Pending, now, None)
+            .unwrap();
+    }
+    let stats = deliver_pending(&db).await;
+    assert_eq!((stats.delivered, stats.failed, stats.dead), (0, 0, 1));
+    assert_eq!(db.get_dead_deliveries().unwrap().len(), 1);
+
+    drop(db);
+    std::fs::remove_file(path).unwrap();
+}